@@ -0,0 +1,136 @@
+use crate::goto::{self, GotoTarget};
+
+use std::path::Path;
+
+pub const USAGE: &str = "\
+Usage: bolt [options] [+line] [file[:line[:column]]]...
+
+A file named - is read from stdin.
+
+Options:
+  +N               Open the following file at line N
+  --readonly       Open every file read-only
+  --stdout         Write the buffer read from stdin to stdout on exit
+  --diff           Compare the two files given side by side
+  --config <path>  Load the config from <path> (default: config.json)
+  --log <path>     Write the log to <path> (default: log.txt)
+  --version        Print the version and exit
+  --help           Print this help and exit
+  --               Treat every following argument as a file name";
+
+pub struct FileArg {
+    pub path: String,
+    // 0-based, like `GotoTarget`.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub readonly: bool,
+    pub stdout: bool,
+    pub diff: bool,
+    pub config: String,
+    pub log: String,
+}
+
+pub enum Invocation {
+    Edit(Args),
+    Help,
+    Version,
+}
+
+pub fn parse(mut arguments: impl Iterator<Item = String>) -> Result<Invocation, String> {
+    let mut args = Args {
+        files: Vec::new(),
+        readonly: false,
+        stdout: false,
+        diff: false,
+        config: String::from("config.json"),
+        log: String::from("log.txt"),
+    };
+    let mut pending_line = None;
+    let mut only_files = false;
+
+    while let Some(argument) = arguments.next() {
+        if only_files {
+            args.files.push(literal(argument, pending_line.take()));
+            continue;
+        }
+        let mut value = |option: &str| {
+            arguments
+                .next()
+                .ok_or_else(|| format!("{option} needs a value"))
+        };
+        match argument.as_str() {
+            "--help" | "-h" => return Ok(Invocation::Help),
+            "--version" | "-V" => return Ok(Invocation::Version),
+            "--readonly" | "-R" => args.readonly = true,
+            "--stdout" => args.stdout = true,
+            "--diff" | "-d" => args.diff = true,
+            "--config" => args.config = value("--config")?,
+            "--log" => args.log = value("--log")?,
+            "--" => only_files = true,
+            _ if argument.starts_with('+') => {
+                let line = argument[1..]
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid line number: '{argument}'"))?;
+                pending_line = Some(line.saturating_sub(1));
+            }
+            _ if argument.starts_with('-') && argument.len() > 1 => {
+                return Err(format!("Unknown option: '{argument}'"));
+            }
+            _ => {
+                let mut file = file_arg(argument);
+                if let Some(line) = pending_line.take() {
+                    file.line = Some(line);
+                }
+                args.files.push(file);
+            }
+        }
+    }
+    if pending_line.is_some() {
+        return Err(String::from("+N must be followed by a file"));
+    }
+    let from_stdin = args.files.iter().filter(|file| file.path == "-").count();
+    if from_stdin > 1 {
+        return Err(String::from("stdin can only be read once"));
+    }
+    if args.stdout && args.files.first().is_none_or(|file| file.path != "-") {
+        return Err(String::from("--stdout needs '-' as the first file"));
+    }
+    if args.diff && args.files.len() != 2 {
+        return Err(String::from("--diff needs exactly two files"));
+    }
+
+    Ok(Invocation::Edit(args))
+}
+
+fn literal(path: String, line: Option<usize>) -> FileArg {
+    FileArg {
+        path,
+        line,
+        column: None,
+    }
+}
+
+// A name like `src/main.rs:12:5`, as printed by compilers, opens the file at
+// that position, unless a file with that exact name exists.
+fn file_arg(argument: String) -> FileArg {
+    if argument.contains(':') && !Path::new(&argument).exists() {
+        if let Ok(GotoTarget {
+            path: Some(path),
+            line,
+            column,
+        }) = goto::parse_goto(&argument, 0, 0)
+        {
+            return FileArg {
+                path,
+                line: Some(line),
+                column,
+            };
+        }
+    }
+
+    literal(argument, None)
+}
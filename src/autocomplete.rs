@@ -0,0 +1,185 @@
+use crate::fuzzy::fuzzy_match;
+use crate::Position;
+
+use std::collections::HashSet;
+
+// Words shorter than this are not worth offering.
+const MIN_WORD_LENGTH: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    LanguageServer,
+    Word,
+    Path,
+}
+
+impl Source {
+    fn label(self) -> &'static str {
+        match self {
+            Source::LanguageServer => "lsp",
+            Source::Word => "word",
+            Source::Path => "path",
+        }
+    }
+}
+
+pub struct Candidate {
+    pub text: String,
+    pub detail: String,
+    pub source: Source,
+}
+
+impl Candidate {
+    pub fn new(text: String, source: Source) -> Self {
+        Self {
+            text,
+            detail: String::new(),
+            source,
+        }
+    }
+}
+
+// The completions offered for the text typed since `start`, narrowed down as
+// more of it is typed.
+pub struct Autocomplete {
+    pub start: Position,
+    // For path completion, the directory whose entries are offered.
+    pub directory: Option<String>,
+    // Whether a language server has yet to answer.
+    pub waiting: bool,
+    candidates: Vec<Candidate>,
+    seen: HashSet<String>,
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Autocomplete {
+    pub fn new(start: Position) -> Self {
+        Self {
+            start,
+            directory: None,
+            waiting: false,
+            candidates: Vec::new(),
+            seen: HashSet::new(),
+            matches: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    // Adds candidates, dropping any whose text is already offered.
+    pub fn add(&mut self, candidates: impl IntoIterator<Item = Candidate>) {
+        for candidate in candidates {
+            if self.seen.insert(candidate.text.clone()) {
+                self.candidates.push(candidate);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.candidates.clear();
+        self.seen.clear();
+        self.matches.clear();
+    }
+
+    // Keeps the candidates matching `typed`, best first, and the selection on
+    // the same candidate when it still matches.
+    pub fn filter(&mut self, typed: &str) {
+        let selected = self.matches.get(self.selected).copied();
+        let mut scored: Vec<(i64, Source, usize)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| candidate.text != typed)
+            .filter_map(|(index, candidate)| {
+                let found = fuzzy_match(typed, &candidate.text)?;
+                Some((found.score, candidate.source, index))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+        self.matches = scored.into_iter().map(|(_, _, index)| index).collect();
+        self.selected = selected
+            .and_then(|selected| self.matches.iter().position(|index| *index == selected))
+            .unwrap_or(0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = self
+                .selected
+                .checked_sub(1)
+                .unwrap_or(self.matches.len() - 1);
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Candidate> {
+        self.matches
+            .get(self.selected)
+            .map(|index| &self.candidates[*index])
+    }
+
+    // Returns at most `height` lines of at most `width` characters, all
+    // padded to the same width and scrolled so the selection is visible,
+    // along with the index of the selected line among them.
+    pub fn lines(&self, height: usize, width: usize) -> (Vec<String>, usize) {
+        let first = (self.selected + 1).saturating_sub(height);
+        let shown: Vec<(&str, &str)> = self
+            .matches
+            .iter()
+            .skip(first)
+            .take(height)
+            .map(|index| {
+                let candidate = &self.candidates[*index];
+                let detail = if candidate.detail.is_empty() {
+                    candidate.source.label()
+                } else {
+                    candidate.detail.as_str()
+                };
+                (candidate.text.as_str(), detail)
+            })
+            .collect();
+        let text_width = shown
+            .iter()
+            .map(|(text, _)| text.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines: Vec<String> = shown
+            .iter()
+            .map(|(text, detail)| {
+                let line = format!(" {text:<text_width$}  {detail} ");
+                line.chars().take(width).collect()
+            })
+            .collect();
+        let line_width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        for line in &mut lines {
+            let padding = line_width - line.chars().count();
+            line.extend(std::iter::repeat_n(' ', padding));
+        }
+
+        (lines, self.selected - first)
+    }
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// The words of `text` worth offering as completions.
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|word| word.chars().count() >= MIN_WORD_LENGTH)
+        .filter(|word| !word.starts_with(|c: char| c.is_ascii_digit()))
+}
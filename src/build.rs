@@ -0,0 +1,186 @@
+use crate::lsp::Severity;
+
+use regex::Regex;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+pub struct BuildMessage {
+    pub path: PathBuf,
+    // Zero-based, with the column counted in characters as compilers do.
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// A build command running in the background, with the messages parsed from
+// its output so far.
+pub struct Build {
+    pub command: String,
+    pub messages: Vec<BuildMessage>,
+    child: Child,
+    lines: Receiver<String>,
+    parser: Parser,
+    status: Option<ExitStatus>,
+}
+
+impl Build {
+    // Runs `command` with the shell in `root`, which relative paths in its
+    // output are taken to be relative to.
+    pub fn start(command: &str, root: &Path) -> io::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(root)
+            .env("CARGO_TERM_COLOR", "never")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (sender, lines) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, sender);
+        }
+
+        Ok(Self {
+            command: command.to_string(),
+            messages: Vec::new(),
+            child,
+            lines,
+            parser: Parser::new(root),
+            status: None,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.is_none()
+    }
+
+    // Parses the output produced since the last call. Returns whether new
+    // messages were found, and the exit status once the command has exited.
+    pub fn poll(&mut self) -> (bool, Option<ExitStatus>) {
+        let count = self.messages.len();
+        if self.status.is_none() {
+            loop {
+                match self.lines.try_recv() {
+                    Ok(line) => self.messages.extend(self.parser.parse(&line)),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.status = self.child.wait().ok();
+                        break;
+                    }
+                }
+            }
+        }
+
+        (self.messages.len() > count, self.status)
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.messages
+            .iter()
+            .filter(|message| message.severity == severity)
+            .count()
+    }
+}
+
+impl Drop for Build {
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn forward_lines(output: impl Read + Send + 'static, sender: Sender<String>) {
+    thread::spawn(move || {
+        for line in BufReader::new(output).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+// Recognises gcc style `file:line:col: error: message` lines, which is also
+// rustc's short format, and rustc's human format, where the location follows
+// the message on a `--> file:line:col` line.
+struct Parser {
+    root: PathBuf,
+    located: Regex,
+    heading: Regex,
+    arrow: Regex,
+    // A message still waiting for its location.
+    pending: Option<(Severity, String)>,
+}
+
+impl Parser {
+    fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            located: Regex::new(
+                r"^([^\s:][^:]*):(\d+):(?:(\d+):)?\s*(fatal error|error|warning|note|help)(?:\[[^\]]*\])?:\s*(.*)$",
+            )
+            .expect("Invalid build output pattern"),
+            heading: Regex::new(r"^(error|warning)(?:\[[^\]]*\])?:\s*(.*)$")
+                .expect("Invalid build output pattern"),
+            arrow: Regex::new(r"^\s*--> (.+?):(\d+):(\d+)$").expect("Invalid build output pattern"),
+            pending: None,
+        }
+    }
+
+    fn parse(&mut self, line: &str) -> Option<BuildMessage> {
+        if let Some(captures) = self.located.captures(line) {
+            self.pending = None;
+            return Some(self.message(
+                &captures[1],
+                &captures[2],
+                captures.get(3).map_or("1", |column| column.as_str()),
+                severity(&captures[4]),
+                &captures[5],
+            ));
+        }
+        if let Some(captures) = self.heading.captures(line) {
+            self.pending = Some((severity(&captures[1]), captures[2].to_string()));
+            return None;
+        }
+        let captures = self.arrow.captures(line)?;
+        let (severity, message) = self.pending.take()?;
+        Some(self.message(&captures[1], &captures[2], &captures[3], severity, &message))
+    }
+
+    fn message(
+        &self,
+        path: &str,
+        line: &str,
+        column: &str,
+        severity: Severity,
+        message: &str,
+    ) -> BuildMessage {
+        let path = self.root.join(path);
+        BuildMessage {
+            path: path.canonicalize().unwrap_or(path),
+            line: line.parse::<usize>().unwrap_or(1).saturating_sub(1),
+            column: column.parse::<usize>().unwrap_or(1).saturating_sub(1),
+            severity,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn severity(kind: &str) -> Severity {
+    match kind {
+        "warning" => Severity::Warning,
+        "note" => Severity::Information,
+        "help" => Severity::Hint,
+        _ => Severity::Error,
+    }
+}
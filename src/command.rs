@@ -0,0 +1,464 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    Quit,
+    Save,
+    SaveAs,
+    WriteCopy,
+    RenameFile,
+    CommandPalette,
+    DeleteBackward,
+    DeleteForward,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    PageUp,
+    PageDown,
+    LineStart,
+    SmartLineStart,
+    LineEnd,
+    WordForward,
+    WordBackward,
+    ParagraphForward,
+    ParagraphBackward,
+    DocumentStart,
+    DocumentEnd,
+    OpenFile,
+    FindFile,
+    Grep,
+    ReplaceInFiles,
+    UndoReplace,
+    Hover,
+    GotoDefinition,
+    FindReferences,
+    Complete,
+    Build,
+    NextError,
+    PreviousError,
+    BuildMessages,
+    FormatBuffer,
+    PipeThrough,
+    InsertCommandOutput,
+    PipeToBuffer,
+    NextHunk,
+    PreviousHunk,
+    ShowHunk,
+    RevertHunk,
+    ToggleBlame,
+    ShowCommit,
+    DiffWithSaved,
+    DiffOtherSide,
+    DiffObtain,
+    DiffPut,
+    DiffOff,
+    NextConflict,
+    PreviousConflict,
+    AcceptOurs,
+    AcceptTheirs,
+    AcceptBoth,
+    AcceptBase,
+    ToggleFold,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    Undo,
+    Redo,
+    ToggleExplorer,
+    FocusExplorer,
+    ToggleReadOnly,
+    NextBuffer,
+    PreviousBuffer,
+    GotoLine,
+    JumpBack,
+    JumpForward,
+    KillLine,
+    KillRegion,
+    CopyRegion,
+    Yank,
+    YankPop,
+    SetMark,
+    Cancel,
+}
+
+const REGISTRY: &[(Command, &str, &str)] = &[
+    (
+        Command::Quit,
+        "quit",
+        "Quit bolt, asking first if there are unsaved changes",
+    ),
+    (Command::Save, "save", "Save the current file"),
+    (
+        Command::SaveAs,
+        "save-as",
+        "Save the buffer under a new name and keep editing that file",
+    ),
+    (
+        Command::WriteCopy,
+        "write-copy",
+        "Write a copy of the buffer to another file",
+    ),
+    (
+        Command::RenameFile,
+        "rename-file",
+        "Rename the buffer's file on disk",
+    ),
+    (
+        Command::CommandPalette,
+        "command-palette",
+        "Search for a command and run it",
+    ),
+    (
+        Command::DeleteBackward,
+        "delete-backward",
+        "Delete the character before the cursor",
+    ),
+    (
+        Command::DeleteForward,
+        "delete-forward",
+        "Delete the character under the cursor",
+    ),
+    (Command::MoveUp, "move-up", "Move the cursor up one line"),
+    (
+        Command::MoveDown,
+        "move-down",
+        "Move the cursor down one line",
+    ),
+    (
+        Command::MoveLeft,
+        "move-left",
+        "Move the cursor left one character",
+    ),
+    (
+        Command::MoveRight,
+        "move-right",
+        "Move the cursor right one character",
+    ),
+    (Command::PageUp, "page-up", "Move the cursor up one screen"),
+    (
+        Command::PageDown,
+        "page-down",
+        "Move the cursor down one screen",
+    ),
+    (
+        Command::LineStart,
+        "line-start",
+        "Move the cursor to the start of the line",
+    ),
+    (
+        Command::SmartLineStart,
+        "smart-line-start",
+        "Move the cursor to the first non-blank character, or to column 0",
+    ),
+    (
+        Command::LineEnd,
+        "line-end",
+        "Move the cursor to the end of the line",
+    ),
+    (
+        Command::WordForward,
+        "move-word-forward",
+        "Move the cursor past the end of the next word",
+    ),
+    (
+        Command::WordBackward,
+        "move-word-backward",
+        "Move the cursor to the start of the previous word",
+    ),
+    (
+        Command::ParagraphForward,
+        "move-paragraph-forward",
+        "Move the cursor to the blank line after the paragraph",
+    ),
+    (
+        Command::ParagraphBackward,
+        "move-paragraph-backward",
+        "Move the cursor to the blank line before the paragraph",
+    ),
+    (
+        Command::DocumentStart,
+        "document-start",
+        "Move the cursor to the start of the document",
+    ),
+    (
+        Command::DocumentEnd,
+        "document-end",
+        "Move the cursor to the end of the document",
+    ),
+    (
+        Command::OpenFile,
+        "open-file",
+        "Open a file in a new buffer, or switch to it if already open",
+    ),
+    (
+        Command::FindFile,
+        "find-file",
+        "Fuzzy find a file in the project and open it in a new buffer",
+    ),
+    (
+        Command::Grep,
+        "grep",
+        "Search the project's files for a regex and list the matches",
+    ),
+    (
+        Command::ReplaceInFiles,
+        "replace-in-files",
+        "Replace a regex across the project, previewing each change",
+    ),
+    (
+        Command::UndoReplace,
+        "undo-replace",
+        "Revert the last replace-in-files in every file it changed",
+    ),
+    (
+        Command::Hover,
+        "hover",
+        "Show the language server's documentation for the symbol under the cursor",
+    ),
+    (
+        Command::GotoDefinition,
+        "goto-definition",
+        "Jump to where the symbol under the cursor is defined",
+    ),
+    (
+        Command::FindReferences,
+        "find-references",
+        "List every use of the symbol under the cursor",
+    ),
+    (
+        Command::Complete,
+        "complete",
+        "Offer completions for the word or path before the cursor",
+    ),
+    (
+        Command::Build,
+        "build",
+        "Run the build command in the background and collect its errors",
+    ),
+    (
+        Command::NextError,
+        "next-error",
+        "Jump to the next error or warning from the last build",
+    ),
+    (
+        Command::PreviousError,
+        "previous-error",
+        "Jump to the previous error or warning from the last build",
+    ),
+    (
+        Command::BuildMessages,
+        "build-messages",
+        "List the errors and warnings from the last build",
+    ),
+    (
+        Command::FormatBuffer,
+        "format-buffer",
+        "Run the buffer through the formatter configured for its file type",
+    ),
+    (
+        Command::PipeThrough,
+        "pipe-through",
+        "Replace the selection, or the buffer, with a shell command's output on it",
+    ),
+    (
+        Command::InsertCommandOutput,
+        "insert-command-output",
+        "Insert what a shell command prints at the cursor",
+    ),
+    (
+        Command::PipeToBuffer,
+        "pipe-to-buffer",
+        "Show a shell command's output on the selection, or the buffer, in a new buffer",
+    ),
+    (
+        Command::NextHunk,
+        "next-hunk",
+        "Jump to the next change in a diff, or since the file's last commit",
+    ),
+    (
+        Command::PreviousHunk,
+        "previous-hunk",
+        "Jump to the previous change in a diff, or since the file's last commit",
+    ),
+    (
+        Command::ShowHunk,
+        "show-hunk",
+        "Show the committed text of the change under the cursor",
+    ),
+    (
+        Command::RevertHunk,
+        "revert-hunk",
+        "Put the change under the cursor back as it was committed",
+    ),
+    (
+        Command::ToggleBlame,
+        "toggle-blame",
+        "Show or hide who last changed each line, and when",
+    ),
+    (
+        Command::ShowCommit,
+        "show-commit",
+        "Open the message of the commit that last changed the cursor's line",
+    ),
+    (
+        Command::DiffWithSaved,
+        "diff-with-saved",
+        "Compare the buffer side by side with its file as last saved",
+    ),
+    (
+        Command::DiffOtherSide,
+        "diff-other-side",
+        "Move the cursor to the other side of the diff",
+    ),
+    (
+        Command::DiffObtain,
+        "diff-obtain",
+        "Replace the change under the cursor with the other side's version",
+    ),
+    (
+        Command::DiffPut,
+        "diff-put",
+        "Replace the other side's version of the change under the cursor with this one",
+    ),
+    (Command::DiffOff, "diff-off", "Stop comparing buffers side by side"),
+    (
+        Command::NextConflict,
+        "next-conflict",
+        "Jump to the next merge conflict",
+    ),
+    (
+        Command::PreviousConflict,
+        "previous-conflict",
+        "Jump to the previous merge conflict",
+    ),
+    (
+        Command::AcceptOurs,
+        "accept-ours",
+        "Resolve the conflict under the cursor with our version",
+    ),
+    (
+        Command::AcceptTheirs,
+        "accept-theirs",
+        "Resolve the conflict under the cursor with their version",
+    ),
+    (
+        Command::AcceptBoth,
+        "accept-both",
+        "Resolve the conflict under the cursor with our version followed by theirs",
+    ),
+    (
+        Command::AcceptBase,
+        "accept-base",
+        "Resolve the conflict under the cursor with the common ancestor's version",
+    ),
+    (
+        Command::ToggleFold,
+        "toggle-fold",
+        "Fold the region around the cursor, or unfold it when it is folded",
+    ),
+    (Command::Fold, "fold", "Fold the innermost region around the cursor"),
+    (Command::Unfold, "unfold", "Unfold the folded region under the cursor"),
+    (Command::FoldAll, "fold-all", "Fold every region in the buffer"),
+    (Command::UnfoldAll, "unfold-all", "Unfold every region in the buffer"),
+    (Command::Undo, "undo", "Undo the last change"),
+    (Command::Redo, "redo", "Redo the last undone change"),
+    (
+        Command::ToggleExplorer,
+        "toggle-explorer",
+        "Show or hide the file explorer sidebar",
+    ),
+    (
+        Command::FocusExplorer,
+        "focus-explorer",
+        "Move the keyboard focus to the file explorer, opening it if needed",
+    ),
+    (
+        Command::ToggleReadOnly,
+        "toggle-read-only",
+        "Make the buffer read-only, or editable again",
+    ),
+    (
+        Command::NextBuffer,
+        "next-buffer",
+        "Switch to the next open buffer",
+    ),
+    (
+        Command::PreviousBuffer,
+        "previous-buffer",
+        "Switch to the previous open buffer",
+    ),
+    (
+        Command::GotoLine,
+        "goto-line",
+        "Go to a line, line:column, +N/-N lines or N% of the document",
+    ),
+    (
+        Command::JumpBack,
+        "jump-back",
+        "Return to where the cursor was before the last jump",
+    ),
+    (
+        Command::JumpForward,
+        "jump-forward",
+        "Redo a jump undone with jump-back",
+    ),
+    (
+        Command::KillLine,
+        "kill-line",
+        "Cut to the end of the line, or the line break at the end",
+    ),
+    (
+        Command::KillRegion,
+        "kill-region",
+        "Cut the text between the mark and the cursor",
+    ),
+    (
+        Command::CopyRegion,
+        "copy-region",
+        "Copy the text between the mark and the cursor",
+    ),
+    (Command::Yank, "yank", "Paste the most recent cut or copy"),
+    (
+        Command::YankPop,
+        "yank-pop",
+        "Replace the text just pasted with the previous kill",
+    ),
+    (
+        Command::SetMark,
+        "set-mark",
+        "Start selecting a region at the cursor",
+    ),
+    (
+        Command::Cancel,
+        "cancel",
+        "Clear the mark and any selection",
+    ),
+];
+
+impl Command {
+    pub fn all() -> impl Iterator<Item = Command> {
+        REGISTRY.iter().map(|(command, _, _)| *command)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn description(self) -> &'static str {
+        self.entry().2
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        REGISTRY
+            .iter()
+            .find(|(_, candidate, _)| *candidate == name)
+            .map(|(command, _, _)| *command)
+    }
+
+    fn entry(self) -> &'static (Command, &'static str, &'static str) {
+        REGISTRY
+            .iter()
+            .find(|(command, _, _)| *command == self)
+            .expect("Command missing from registry")
+    }
+}
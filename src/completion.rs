@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+pub struct PathCompletion {
+    // The input extended by as much as all candidates have in common.
+    pub text: String,
+    // File names matching the input, when there is more than one.
+    pub candidates: Vec<String>,
+}
+
+// Completes the last component of `input` against the entries of its
+// directory. Directories are completed with a trailing '/', and dot files are
+// only offered once the component itself starts with a dot.
+pub fn complete_path(input: &str) -> PathCompletion {
+    let (directory, prefix) = split_path(input);
+    let mut names = directory_entries(input);
+
+    let common = match names.as_slice() {
+        [] => prefix.to_string(),
+        [first, rest @ ..] => rest.iter().fold(first.clone(), |common, name| {
+            common
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        }),
+    };
+    if names.len() == 1 {
+        names.clear();
+    }
+
+    PathCompletion {
+        text: format!("{directory}{common}"),
+        candidates: names,
+    }
+}
+
+// Splits `input` into its directory, including the trailing '/', and the
+// component being typed.
+pub fn split_path(input: &str) -> (&str, &str) {
+    match input.rfind('/') {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    }
+}
+
+// The sorted names in the directory of `input` that its last component is a
+// prefix of.
+pub fn directory_entries(input: &str) -> Vec<String> {
+    let (directory, prefix) = split_path(input);
+    let search = if directory.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(directory)
+    };
+
+    let mut names: Vec<String> = fs::read_dir(search)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let mut name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            Some(name)
+        })
+        .collect();
+    names.sort();
+    names
+}
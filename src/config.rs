@@ -1,21 +1,80 @@
+use std::collections::HashMap;
 use std::fs;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use serde::Deserialize;
 use termion::color;
 use regex::Regex;
 
+use crate::keymap::Preset;
+use crate::Keymap;
+
+const DEFAULT_BUILD_COMMAND: &str = "cargo build";
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct RawConfig {
     status_line_bg_color: String,
     status_line_fg_color: String,
+    #[serde(default)]
+    keymap: Option<String>,
+    #[serde(default)]
+    keybindings: HashMap<String, String>,
+    #[serde(default)]
+    language_servers: HashMap<String, LanguageServerConfig>,
+    #[serde(default)]
+    build_command: Option<String>,
+    #[serde(default)]
+    formatters: HashMap<String, FormatterConfig>,
+}
+
+// How to run the language server for one language, keyed by its language
+// id, and which file extensions belong to it.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub extensions: Vec<String>,
+}
+
+// A command that reads a file's text on stdin and writes it back formatted.
+// `{file}` in its arguments stands for the file's path.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatterConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub extensions: Vec<String>,
+    #[serde(default = "format_on_save")]
+    pub on_save: bool,
+}
+
+fn format_on_save() -> bool {
+    true
 }
 
 impl RawConfig {
    fn to_config(&self) -> Result<Config, Error> {
+       let preset = match &self.keymap {
+           Some(name) => Preset::from_name(name)?,
+           None => Preset::Standard,
+       };
+       let mut keymap = Keymap::for_preset(preset);
+       keymap.apply_overrides(&self.keybindings)?;
+
        let result = Config {
            status_line_fg_color: parse_rgb_string(&self.status_line_fg_color)?,
            status_line_bg_color: parse_rgb_string(&self.status_line_bg_color)?,
+           keymap,
+           preset,
+           language_servers: self.language_servers.clone(),
+           build_command: self
+               .build_command
+               .clone()
+               .unwrap_or_else(|| String::from(DEFAULT_BUILD_COMMAND)),
+           formatters: self.formatters.clone(),
        };
 
        Ok(result)
@@ -24,11 +83,12 @@ impl RawConfig {
 
 fn parse_rgb_string(string: &str) -> Result<color::Rgb, Error> {
     let re = Regex::new(r"rgb\((\d{1,3}),\s?(\d{1,3}),\s?(\d{1,3})\)").unwrap();
-    let captures = re.captures(string).expect("");
+    let invalid = || Error::new(ErrorKind::InvalidData, format!("Invalid colour '{string}'"));
+    let captures = re.captures(string).ok_or_else(invalid)?;
 
-    let red: u8 = captures.get(1).unwrap().as_str().parse().unwrap();
-    let green: u8 = captures.get(2).unwrap().as_str().parse().unwrap();
-    let blue: u8 = captures.get(3).unwrap().as_str().parse().unwrap();
+    let red: u8 = captures[1].parse().map_err(|_| invalid())?;
+    let green: u8 = captures[2].parse().map_err(|_| invalid())?;
+    let blue: u8 = captures[3].parse().map_err(|_| invalid())?;
 
     Ok(color::Rgb(red, green, blue))
 }
@@ -37,9 +97,28 @@ fn parse_rgb_string(string: &str) -> Result<color::Rgb, Error> {
 pub struct Config {
     pub status_line_bg_color: color::Rgb,
     pub status_line_fg_color: color::Rgb,
+    pub keymap: Keymap,
+    pub preset: Preset,
+    pub language_servers: HashMap<String, LanguageServerConfig>,
+    pub build_command: String,
+    pub formatters: HashMap<String, FormatterConfig>,
 }
 
 impl Config {
+    // The language id and server for files like `filename`.
+    pub fn language_server(&self, filename: &str) -> Option<(&str, &LanguageServerConfig)> {
+        self.language_servers
+            .iter()
+            .find(|(_, server)| has_extension(filename, &server.extensions))
+            .map(|(language, server)| (language.as_str(), server))
+    }
+
+    pub fn formatter(&self, filename: &str) -> Option<&FormatterConfig> {
+        self.formatters
+            .values()
+            .find(|formatter| has_extension(filename, &formatter.extensions))
+    }
+
     pub fn load(filename: &str) -> Result<Config, std::io::Error> {
         let raw_config = Config::load_raw(filename)?;
         let config = raw_config.to_config()?;
@@ -53,4 +132,11 @@ impl Config {
 
         Ok(raw_config)
     }
+}
+
+fn has_extension(filename: &str, extensions: &[String]) -> bool {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.iter().any(|candidate| candidate == extension))
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+use crate::{Document, Row};
+
+use std::ops::Range;
+
+// A region a merge left for resolving by hand, as rows of the document.
+// The ranges are the rows between the markers; `base` is only there when
+// the merge wrote the common ancestor too, as git's diff3 style does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub start: usize,
+    pub ours: Range<usize>,
+    pub base: Option<Range<usize>>,
+    pub theirs: Range<usize>,
+    pub end: usize,
+}
+
+impl Conflict {
+    pub fn contains(&self, y: usize) -> bool {
+        self.start <= y && y <= self.end
+    }
+
+    // Whether row `y` is one of the marker lines.
+    pub fn is_marker(&self, y: usize) -> bool {
+        y == self.start
+            || y == self.end
+            || y == self.ours.end
+            || self.base.as_ref().is_some_and(|base| y == base.end)
+    }
+}
+
+// The conflicts in `document`, top to bottom. Markers that do not make up a
+// whole conflict are ignored.
+pub fn find(document: &Document) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    // The rows of the opening marker, and of the base and separator markers
+    // once they are seen.
+    let mut open: Option<(usize, Option<usize>, Option<usize>)> = None;
+
+    for y in 0..document.len() {
+        let Some(line) = document.row(y).map(Row::as_str) else {
+            break;
+        };
+        if is_marker(line, "<<<<<<<") {
+            open = Some((y, None, None));
+            continue;
+        }
+        let Some((start, base, separator)) = open.as_mut() else {
+            continue;
+        };
+        if separator.is_none() && base.is_none() && is_marker(line, "|||||||") {
+            *base = Some(y);
+        } else if separator.is_none() && line.trim_end() == "=======" {
+            *separator = Some(y);
+        } else if is_marker(line, ">>>>>>>") {
+            if let Some(separator) = *separator {
+                conflicts.push(Conflict {
+                    start: *start,
+                    ours: *start + 1..base.unwrap_or(separator),
+                    base: base.map(|base| base + 1..separator),
+                    theirs: separator + 1..y,
+                    end: y,
+                });
+            }
+            open = None;
+        }
+    }
+
+    conflicts
+}
+
+// Markers are seven characters, optionally followed by a label.
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
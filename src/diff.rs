@@ -0,0 +1,126 @@
+use std::ops::Range;
+
+// Past this many inserted and deleted lines the differing middle is given up
+// on and reported as one change, which keeps the search's memory bounded.
+const MAX_EDITS: usize = 2000;
+
+// A run of lines that differs: `old` was replaced by `new`. Either may be
+// empty, for lines that were only added or only deleted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+// The hunks that turn `old` into `new`, in order, found with Myers' algorithm
+// after setting aside what the two have in common at either end.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if a.is_empty() && b.is_empty() {
+        return Vec::new();
+    }
+
+    let Some(matches) = matching_lines(a, b) else {
+        return vec![Hunk {
+            old: prefix..old.len() - suffix,
+            new: prefix..new.len() - suffix,
+        }];
+    };
+
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (match_x, match_y) in matches.into_iter().chain([(a.len(), b.len())]) {
+        if match_x > x || match_y > y {
+            hunks.push(Hunk {
+                old: prefix + x..prefix + match_x,
+                new: prefix + y..prefix + match_y,
+            });
+        }
+        x = match_x + 1;
+        y = match_y + 1;
+    }
+
+    hunks
+}
+
+// The pairs of equal lines along a shortest edit script, or None when it
+// would take more than MAX_EDITS edits.
+fn matching_lines<T: PartialEq>(old: &[T], new: &[T]) -> Option<Vec<(usize, usize)>> {
+    let old_len = to_isize(old.len());
+    let new_len = to_isize(new.len());
+    let max = usize::try_from(old_len + new_len)
+        .unwrap_or(0)
+        .min(MAX_EDITS);
+    let offset = to_isize(max) + 1;
+    let index = |k: isize| usize::try_from(k + offset).unwrap_or(0);
+
+    // For each diagonal k = x - y, the furthest x reached so far. The values
+    // before each round are kept for walking back along the path.
+    let mut furthest = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = None;
+    'search: for d in 0..=to_isize(max) {
+        trace.push(furthest[index(-d)..=index(d)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+                furthest[index(k + 1)]
+            } else {
+                furthest[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < old_len && y < new_len && old[to_usize(x)] == new[to_usize(y)] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index(k)] = x;
+            if x >= old_len && y >= new_len {
+                found = Some(d);
+                break 'search;
+            }
+        }
+    }
+    let last = found?;
+
+    let mut matches = Vec::new();
+    let (mut x, mut y) = (old_len, new_len);
+    for d in (0..=last).rev() {
+        let before = &trace[to_usize(d)];
+        let at = |k: isize| before[to_usize(k + d)];
+        let k = x - y;
+        let previous_k = if d == 0 {
+            k
+        } else if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = if d == 0 { 0 } else { at(previous_k) };
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            matches.push((to_usize(x), to_usize(y)));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    matches.reverse();
+
+    Some(matches)
+}
+
+fn to_isize(value: usize) -> isize {
+    isize::try_from(value).unwrap_or(isize::MAX)
+}
+
+fn to_usize(value: isize) -> usize {
+    usize::try_from(value).unwrap_or(0)
+}
@@ -1,44 +1,121 @@
+use crate::undo::{Edit, UndoStack};
 use crate::Position;
 use crate::Row;
 
+use std::cell::RefCell;
 use std::fs;
 use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Default, Debug)]
 pub struct Document {
     pub filename: Option<String>,
     rows: Vec<Row>,
     dirty: bool,
+    read_only: bool,
+    edit_refused: bool,
+    undo: UndoStack,
+    // Changes not yet sent to a language server, once one is listening.
+    changes: Option<Vec<RowChange>>,
+    // The filename last resolved to a canonical path, with that path.
+    canonical: RefCell<Option<(String, PathBuf)>>,
+    version: Version,
+}
+
+// Names the state of a document's text, so that what was worked out from it
+// is only worked out again once it changes. Versions come from a counter
+// shared by every document, so no two documents ever have the same one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Version(u64);
+
+impl Version {
+    fn next() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        Self(COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Self::next()
+    }
+}
+
+// `removed` rows starting at `at` were replaced by the `inserted` ones.
+#[derive(Debug)]
+pub struct RowChange {
+    pub at: usize,
+    pub removed: usize,
+    pub inserted: Vec<String>,
 }
 
 impl Document {
     pub fn open(filename: &str) -> Result<Self, std::io::Error> {
         let contents = fs::read_to_string(filename)?;
-        let mut rows = Vec::new();
-        for value in contents.lines() {
-            rows.push(Row::from(value));
-        }
 
         Ok(Self {
-            rows,
             filename: Some(filename.to_string()),
-            dirty: false
+            read_only: !is_writable(filename),
+            ..Self::from_text(&contents)
         })
     }
 
+    // An unnamed document holding `contents`, such as text read from stdin.
+    pub fn from_text(contents: &str) -> Self {
+        Self {
+            rows: contents.lines().map(Row::from).collect(),
+            ..Self::default()
+        }
+    }
+
+    // Opens `filename`, or starts an empty document that will be saved there
+    // if the file does not exist yet.
+    pub fn open_or_new(filename: &str) -> Result<Self, std::io::Error> {
+        match Self::open(filename) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self {
+                filename: Some(filename.to_string()),
+                ..Self::default()
+            }),
+            result => result,
+        }
+    }
+
     pub fn save(&mut self) -> Result<(), std::io::Error> {
         if let Some(filename) = &self.filename {
-            let mut file = fs::File::create(filename)?;
-            for row in &self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n");
-            }
-
+            self.write_to(filename)?;
             self.dirty = false;
         }
         Ok(())
     }
 
+    // Writes the text to `filename` without making it the document's file.
+    pub fn write_to(&self, filename: &str) -> Result<(), std::io::Error> {
+        let mut file = fs::File::create(filename)?;
+        for row in &self.rows {
+            file.write_all(row.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    // The text as it would be saved, with a newline after every row.
+    pub fn contents(&self) -> String {
+        let mut contents = String::new();
+        for row in &self.rows {
+            contents.push_str(row.as_str());
+            contents.push('\n');
+        }
+
+        contents
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
     }
@@ -49,51 +126,326 @@ impl Document {
 
     pub fn is_dirty(&self) -> bool { self.dirty }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    // Whether an edit was refused because the document is read-only since
+    // the last call, so the editor can say why nothing happened.
+    pub fn take_refused_edit(&mut self) -> bool {
+        std::mem::take(&mut self.edit_refused)
+    }
+
+    fn refuse_edit(&mut self) -> bool {
+        self.edit_refused |= self.read_only;
+        self.read_only
+    }
+
     pub fn len(&self) -> usize {
         self.rows.len()
     }
 
     pub fn insert(&mut self, at: &Position, c: char) {
-        if at.y > self.len() {
+        if at.y > self.len() || self.refuse_edit() {
             return;
         }
-        self.dirty = true;
-        if c == '\n' {
-            self.insert_newline(at);
+        let end = (at.y + 1).min(self.len());
+        self.change_rows(at.y..end, |rows| {
+            if c == '\n' {
+                insert_newline(rows, at);
+            } else if at.y == rows.len() {
+                let mut row = Row::default();
+                row.insert(0, c);
+                rows.push(row);
+            } else {
+                rows[at.y].insert(at.x, c);
+            }
+        });
+    }
+
+    pub fn delete(&mut self, at: &Position) {
+        let len = self.len();
+        if at.y >= len || self.refuse_edit() {
             return;
         }
-        if at.y == self.len() {
-            let mut row = Row::default();
-            row.insert(0, c);
-            self.rows.push(row);
-        } else {
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.insert(at.x, c);
+        let end = (at.y + 2).min(len);
+        self.change_rows(at.y..end, |rows| {
+            if at.x == rows[at.y].len() && at.y < len - 1 {
+                let next_row = rows.remove(at.y + 1);
+                rows[at.y].append(&next_row);
+            } else {
+                rows[at.y].delete(at.x);
+            }
+        });
+    }
+
+    // Inserts `text`, which may span several lines, and returns the position
+    // just after the inserted text.
+    pub fn insert_str(&mut self, at: &Position, text: &str) -> Position {
+        if at.y > self.len() || text.is_empty() || self.refuse_edit() {
+            return *at;
         }
+        let last = (at.y + 1).min(self.len());
+        self.change_rows(at.y..last, |rows| {
+            if at.y == rows.len() {
+                rows.push(Row::default());
+            }
+            let tail = rows[at.y].split(at.x);
+            let mut lines = text.split('\n');
+            let mut end = *at;
+            if let Some(first) = lines.next() {
+                let row = &mut rows[at.y];
+                row.insert_str(at.x, first);
+                end.x = row.len();
+            }
+            for line in lines {
+                end.y += 1;
+                let row = Row::from(line);
+                end.x = row.len();
+                rows.insert(end.y, row);
+            }
+            rows[end.y].append(&tail);
+
+            end
+        })
     }
 
-    pub fn delete(&mut self, at: &Position) {
-        let len = self.len();
-        if at.y >= len {
+    // Deletes the text between `start` (inclusive) and `end` (exclusive). An
+    // end position past the last row deletes through the end of the document.
+    pub fn delete_range(&mut self, start: &Position, end: &Position) {
+        let (start, mut end) = ordered(*start, *end);
+        if start.y >= self.len() || start == end || self.refuse_edit() {
             return;
         }
+        if end.y >= self.len() {
+            end = self.end_position();
+        }
+        self.change_rows(start.y..end.y + 1, |rows| {
+            if start.y == end.y {
+                rows[start.y].delete_range(start.x, end.x);
+                return;
+            }
+            let tail = rows[end.y].split(end.x);
+            rows.drain(start.y + 1..=end.y);
+            let row = &mut rows[start.y];
+            row.delete_range(start.x, row.len());
+            row.append(&tail);
+        });
+    }
+
+    pub fn text_range(&self, start: &Position, end: &Position) -> String {
+        let (start, end) = ordered(*start, *end);
+        let mut text = String::new();
+        for y in start.y..=end.y {
+            let Some(row) = self.rows.get(y) else {
+                break;
+            };
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { row.len() };
+            text.push_str(&row.slice(from, to));
+            if y < end.y && y + 1 < self.len() {
+                text.push('\n');
+            }
+        }
+
+        text
+    }
+
+    pub fn replace_row(&mut self, index: usize, text: &str) {
+        let changed = self.rows.get(index).is_some_and(|row| row.as_str() != text);
+        if changed && !self.refuse_edit() {
+            self.change_rows(index..index + 1, |rows| rows[index] = Row::from(text));
+        }
+    }
+
+    // Replaces the rows in `range` with the lines of `text`.
+    pub fn replace_lines(&mut self, range: Range<usize>, text: &str) {
+        let range = range.start.min(self.len())..range.end.min(self.len());
+        if !self.refuse_edit() {
+            self.change_rows(range.clone(), |rows| {
+                rows.splice(range, text.lines().map(Row::from));
+            });
+        }
+    }
+
+    // Applies `change` to the rows, recording how those in `range` changed
+    // so that it can be undone. Rows outside `range` must be left alone.
+    fn change_rows<T>(&mut self, range: Range<usize>, change: impl FnOnce(&mut Vec<Row>) -> T) -> T {
+        let before = self.rows[range.clone()].to_vec();
+        let old_len = self.rows.len();
+        let result = change(&mut self.rows);
+        let end = range.end + self.rows.len() - old_len;
+        let after = self.rows[range.start..end].to_vec();
+        self.note_change(range.start, before.len(), &after);
+        self.undo.record(Edit {
+            at: range.start,
+            before,
+            after,
+        });
+        self.dirty = true;
+
+        result
+    }
+
+    // Ends the current undo step; the editor calls this between commands.
+    pub fn commit_edits(&mut self) {
+        self.undo.commit();
+    }
+
+    // Reverts the last step of edits, returning where the first of them was
+    // made.
+    pub fn undo(&mut self) -> Option<Position> {
+        if self.refuse_edit() {
+            return None;
+        }
+        let step = self.undo.undo()?;
+        for edit in step.iter().rev() {
+            let range = edit.at..edit.at + edit.after.len();
+            self.rows.splice(range, edit.before.iter().cloned());
+            if let Some(changes) = self.changes.as_mut() {
+                changes.push(RowChange::new(edit.at, edit.after.len(), &edit.before));
+            }
+        }
         self.dirty = true;
-        if at.x == self.rows.get_mut(at.y).unwrap().len() && at.y < len - 1 {
-            let next_row = self.rows.remove(at.y + 1);
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.append(&next_row);
-        } else {
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.delete(at.x);
+        self.version = Version::next();
+
+        step.first()
+            .map(|edit| first_difference(edit.at, &edit.after, &edit.before))
+    }
+
+    pub fn redo(&mut self) -> Option<Position> {
+        if self.refuse_edit() {
+            return None;
+        }
+        let step = self.undo.redo()?;
+        for edit in step {
+            let range = edit.at..edit.at + edit.before.len();
+            self.rows.splice(range, edit.after.iter().cloned());
+            if let Some(changes) = self.changes.as_mut() {
+                changes.push(RowChange::new(edit.at, edit.before.len(), &edit.after));
+            }
+        }
+        self.dirty = true;
+        self.version = Version::next();
+
+        step.first()
+            .map(|edit| first_difference(edit.at, &edit.before, &edit.after))
+    }
+
+    // Replaces all of the text as a single edit.
+    pub fn set_contents(&mut self, contents: &str) {
+        if !self.refuse_edit() {
+            self.change_rows(0..self.len(), |rows| {
+                *rows = contents.lines().map(Row::from).collect();
+            });
+        }
+    }
+
+    // Adds a row at the end without marking the document modified, even when
+    // it is read-only, for lists the editor fills in itself.
+    pub fn append_line(&mut self, text: &str) {
+        let row = Row::from(text);
+        self.note_change(self.len(), 0, std::slice::from_ref(&row));
+        self.rows.push(row);
+    }
+
+    // Starts collecting changes for `take_changes`.
+    pub fn track_changes(&mut self) {
+        self.changes.get_or_insert_with(Vec::new);
+    }
+
+    pub fn tracks_changes(&self) -> bool {
+        self.changes.is_some()
+    }
+
+    // The canonical path of the file, resolved again only once the filename
+    // changes.
+    pub fn canonical_path(&self) -> Option<PathBuf> {
+        let filename = self.filename.as_ref()?;
+        let mut canonical = self.canonical.borrow_mut();
+        if let Some((resolved, path)) = canonical.as_ref() {
+            if resolved == filename {
+                return Some(path.clone());
+            }
         }
+        let path = Path::new(filename).canonicalize().ok()?;
+        *canonical = Some((filename.clone(), path.clone()));
+        Some(path)
+    }
+
+    pub fn take_changes(&mut self) -> Vec<RowChange> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    fn note_change(&mut self, at: usize, removed: usize, inserted: &[Row]) {
+        self.version = Version::next();
+        if let Some(changes) = self.changes.as_mut() {
+            changes.push(RowChange::new(at, removed, inserted));
+        }
+    }
+
+    pub fn end_position(&self) -> Position {
+        self.rows.last().map_or_else(Position::default, |row| Position {
+            x: row.len(),
+            y: self.len() - 1,
+        })
     }
 
-    fn insert_newline(&mut self, at: &Position) {
-        if at.y == self.len() {
-            self.rows.push(Row::default());
-            return
+}
+
+impl RowChange {
+    fn new(at: usize, removed: usize, inserted: &[Row]) -> Self {
+        Self {
+            at,
+            removed,
+            inserted: inserted.iter().map(|row| row.as_str().to_string()).collect(),
         }
-        let new_row = self.rows.get_mut(at.y).unwrap().split(at.x);
-        self.rows.insert(at.y + 1, new_row);
     }
 }
+
+fn insert_newline(rows: &mut Vec<Row>, at: &Position) {
+    if at.y == rows.len() {
+        rows.push(Row::default());
+        return
+    }
+    let new_row = rows[at.y].split(at.x);
+    rows.insert(at.y + 1, new_row);
+}
+
+// Where row `at` first differs between `old` and `new`, which is where the
+// cursor goes after an undo or redo.
+fn first_difference(at: usize, old: &[Row], new: &[Row]) -> Position {
+    let x = match (old.first(), new.first()) {
+        (Some(old), Some(new)) => old
+            .graphemes()
+            .iter()
+            .zip(new.graphemes())
+            .take_while(|(a, b)| *a == b)
+            .count(),
+        _ => 0,
+    };
+
+    Position { x, y: at }
+}
+
+fn ordered(a: Position, b: Position) -> (Position, Position) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+// Opening for writing without truncating tells whether saving would succeed,
+// which the permission bits alone do not.
+fn is_writable(filename: &str) -> bool {
+    fs::OpenOptions::new().write(true).open(filename).is_ok()
+}
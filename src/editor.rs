@@ -1,24 +1,90 @@
-use crate::{Config, Document};
+use crate::keymap::{self, Lookup, Preset};
+use crate::autocomplete::Autocomplete;
+use crate::build::Build;
+use crate::explorer::Explorer;
+use crate::finder::Finder;
+use crate::jumplist::JumpList;
+use crate::lineedit::History;
+use crate::killring::KillRing;
+use crate::palette::Palette;
+use crate::motion;
+use crate::vim::{Mode, Vim};
+use crate::{Command, Config, Document};
 use crate::Logger;
 use crate::Row;
 use crate::Terminal;
 
-use std::env;
+use conflict::Resolution;
+use prompt::PromptKind;
+use std::collections::HashMap;
+use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
 use std::time::{Duration, Instant};
-use termion::color;
 use termion::event::Key;
 
-const STATUS_FG_COLOR: color::Rgb = color::Rgb(50, 50, 50);
-const STATUS_BG_COLOR: color::Rgb = color::Rgb(239, 239, 239);
-const LINE_NUMBER_FG_COLOR: color::Rgb = color::Rgb(120, 120, 120);
+mod autocomplete;
+mod blame;
+mod buffers;
+mod conflict;
+mod diffview;
+mod build;
+mod files;
+mod finder;
+mod folding;
+mod format;
+mod gitgutter;
+mod grep;
+mod gutter;
+mod lsp;
+mod modal;
+mod navigation;
+mod prompt;
+mod region;
+mod replace;
+mod shell;
+mod sidebar;
+
+pub use buffers::Buffer;
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+const PALETTE_HEIGHT: usize = 10;
+// How often to check on background work while waiting for a key.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const READ_ONLY_MESSAGE: &str = "Buffer is read-only";
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
 }
 
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.y.cmp(&other.y).then(self.x.cmp(&other.x))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    // The selection stops just before the cursor.
+    Exclusive,
+    // The character under the cursor is part of the selection.
+    Inclusive,
+    Line,
+}
+
+#[derive(Clone, Copy)]
+pub struct Selection {
+    pub anchor: Position,
+    pub mode: SelectionMode,
+}
+
 struct StatusMessage {
     text: String,
     time: Instant,
@@ -40,39 +106,127 @@ pub struct Editor<'a> {
     cursor_position: Position,
     offset: Position,
     document: Document,
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
     status_message: StatusMessage,
+    pending_keys: Vec<Key>,
+    palette: Option<Palette>,
+    finder: Option<Finder>,
+    autocomplete: Option<Autocomplete>,
+    grep: Option<grep::Grep>,
+    replace: Option<replace::Replace>,
+    last_replace: Option<replace::AppliedReplace>,
+    lsp: lsp::LspState,
+    build: Option<Build>,
+    // The build message last moved to.
+    build_index: Option<usize>,
+    git_gutter: Option<gitgutter::GitGutter>,
+    blame: Option<blame::Blame>,
+    // The buffer showing a commit, reused for the next commit shown.
+    commit_copy: Option<usize>,
+    diff: Option<diffview::DiffView>,
+    // The buffer holding a file as last saved, reused by every comparison
+    // with the saved file.
+    saved_copy: Option<usize>,
+    conflicts: Vec<crate::conflict::Conflict>,
+    // The version of the document the conflicts were found in.
+    conflicts_version: Option<crate::document::Version>,
+    folds: Vec<crate::fold::Fold>,
+    // The version of the document the folds were found in.
+    folds_version: Option<crate::document::Version>,
+    // The first rows of the folds that are closed.
+    closed_folds: Vec<usize>,
+    // Where the cursor sits in the message bar while a prompt is open.
+    prompt_cursor: Option<usize>,
+    completions: Vec<String>,
+    // Lines shown over the text until the next key, such as documentation.
+    info: Vec<String>,
+    histories: HashMap<PromptKind, History>,
+    explorer: Option<Explorer>,
+    explorer_focused: bool,
+    selection: Option<Selection>,
+    vim: Option<Vim>,
+    kill_ring: KillRing,
+    last_command: Option<Command>,
+    last_yank: Option<(Position, Position)>,
+    jump_list: JumpList,
+    // The text to write to stdout on exit, when the first buffer is a filter.
+    stdout: Option<String>,
     config: Config,
     logger: &'a Logger,
 }
 
 impl<'a> Editor<'a> {
-    pub fn new(config: Config, logger: &'a Logger) -> Self {
-        let args: Vec<String> = env::args().collect();
-        let mut initial_status = String::from("HELP: Ctrl-S = Save | Ctrl-Q = quit");
-        let document = if args.len() > 1 {
-            let filename = &args[1];
-            let doc = Document::open(&filename);
-            if doc.is_ok() {
-                doc.unwrap()
-            } else {
-                initial_status = format!("ERR: Could not open file: {}", filename);
-                Document::default()
-            }
-        } else {
-            Document::default()
-        };
+    pub fn new(config: Config, logger: &'a Logger, mut buffers: Vec<Buffer>) -> Self {
+        if buffers.is_empty() {
+            buffers.push(Buffer::default());
+        }
 
-        Self {
+        let mut editor = Self {
             should_quit: false,
             confirm_quit: false,
             terminal: Terminal::new(logger).expect("Failed to initialise terminal"),
             cursor_position: Position::default(),
             offset: Position::default(),
-            document,
-            status_message: StatusMessage::from(initial_status),
+            document: Document::default(),
+            buffers,
+            current_buffer: 0,
+            status_message: StatusMessage::from(String::from(
+                "HELP: Ctrl-S = Save | Ctrl-Q = quit",
+            )),
+            pending_keys: Vec::new(),
+            palette: None,
+            finder: None,
+            autocomplete: None,
+            grep: None,
+            replace: None,
+            last_replace: None,
+            lsp: lsp::LspState::default(),
+            build: None,
+            build_index: None,
+            git_gutter: None,
+            blame: None,
+            commit_copy: None,
+            diff: None,
+            saved_copy: None,
+            conflicts: Vec::new(),
+            conflicts_version: None,
+            folds: Vec::new(),
+            folds_version: None,
+            closed_folds: Vec::new(),
+            prompt_cursor: None,
+            completions: Vec::new(),
+            info: Vec::new(),
+            histories: HashMap::new(),
+            explorer: None,
+            explorer_focused: false,
+            selection: None,
+            vim: (config.preset == Preset::Vim).then(Vim::new),
+            kill_ring: KillRing::default(),
+            last_command: None,
+            last_yank: None,
+            jump_list: JumpList::default(),
+            stdout: None,
             config,
             logger,
-        }
+        };
+        editor.show_buffer(0);
+        editor.lsp_sync();
+        editor.update_git_gutter();
+        editor.update_conflicts();
+        editor.update_folds(None);
+
+        editor
+    }
+
+    // Makes the first buffer a filter: unless it is saved under a name, what
+    // was last saved of it is written to stdout when the editor exits.
+    pub fn send_to_stdout(&mut self) {
+        self.stdout = Some(self.document.contents());
+    }
+
+    pub fn stdout_text(&mut self) -> Option<String> {
+        self.stdout.take()
     }
 
     pub fn run(&mut self) {
@@ -80,7 +234,7 @@ impl<'a> Editor<'a> {
         loop {
             if let Err(err) = self.refresh_screen() {
                 self.logger
-                    .error(&format!("Error refreshing screen: {}", err));
+                    .error(&format!("Error refreshing screen: {err}"));
                 die(&err);
             }
 
@@ -91,7 +245,7 @@ impl<'a> Editor<'a> {
 
             if let Err(err) = self.process_keypress() {
                 self.logger
-                    .error(&format!("Error handling keypress: {}", err));
+                    .error(&format!("Error handling keypress: {err}"));
                 die(&err);
             }
         }
@@ -108,10 +262,28 @@ impl<'a> Editor<'a> {
             self.draw_rows();
             self.draw_status_bar();
             self.draw_message_bar();
-            Terminal::set_cursor_position(&Position {
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
-                y: self.cursor_position.y.saturating_sub(self.offset.y),
-            });
+            if let Some(palette) = &self.palette {
+                self.draw_palette(palette);
+            } else if let Some(finder) = &self.finder {
+                self.draw_finder(finder);
+            } else if !self.completions.is_empty() {
+                self.draw_completions();
+            } else if let Some(popup) = &self.autocomplete {
+                self.draw_autocomplete(popup);
+            } else if !self.info.is_empty() {
+                self.draw_overlay(&self.info, None);
+            }
+            let cursor = match self.prompt_cursor {
+                Some(x) => Position {
+                    x,
+                    y: self.terminal.size().height as usize,
+                },
+                None => self.diff_cursor().unwrap_or(Position {
+                    x: self.cursor_position.x.saturating_sub(self.offset.x) + self.text_left(),
+                    y: self.screen_row(self.cursor_position.y),
+                }),
+            };
+            Terminal::set_cursor_position(&cursor);
         }
         Terminal::cursor_show();
         Terminal::flush()
@@ -119,12 +291,41 @@ impl<'a> Editor<'a> {
 
     fn draw_rows(&self) {
         let height = self.terminal.size().height;
+        let sidebar = self
+            .explorer
+            .as_ref()
+            .map(|explorer| explorer.lines(self.text_height(), sidebar::SIDEBAR_WIDTH - 1));
+        let preview = self.finder.as_ref().map(Finder::preview);
+        let gutter = preview.is_none() && self.gutter_width() > 0;
 
+        let mut next_y = self.offset.y;
         for row_index in 0..height - 1 {
             Terminal::clear_current_line();
+            if let Some((lines, selected)) = &sidebar {
+                let index = row_index as usize;
+                let line = lines.get(index).map_or("", String::as_str);
+                self.draw_sidebar_line(line, index == *selected);
+            }
 
-            if let Some(row) = self.document.row(row_index as usize + self.offset.y) {
-                self.render_row(row, row_index + 1);
+            if preview.is_none() && self.is_diff_shown() {
+                self.draw_diff_line(row_index as usize);
+                continue;
+            }
+            // Rows in closed folds are skipped.
+            let y = next_y;
+            next_y = self.row_below(y);
+            if gutter {
+                self.draw_gutter(y);
+            }
+            if let Some(preview) = preview {
+                let line = preview.get(row_index as usize).map_or("", String::as_str);
+                let line: String = line.graphemes(true).take(self.text_width()).collect();
+                println!("{line}\r");
+            } else if let Some(row) = self.document.row(y) {
+                match self.closed_fold_at(y) {
+                    Some(fold) => self.render_fold(row, fold),
+                    None => self.render_row(row, y),
+                }
             } else if self.document.is_empty() && row_index == height / 3 {
                 self.draw_welcome_message();
             } else {
@@ -133,24 +334,80 @@ impl<'a> Editor<'a> {
         }
     }
 
-    pub fn render_row(&self, row: &Row, index: u16) {
-        let width = self.terminal.size().width as usize;
+    pub fn render_row(&self, row: &Row, y: usize) {
+        let width = self.text_width();
         let start = self.offset.x;
         let end = self.offset.x + width;
-        let row = row.render(start, end);
+        let rendered = row.render(start, end);
+        let selected = self.selected_columns(y, row.len());
+        let underlined = self.diagnostic_columns(y, row);
+        let background = self.conflict_color(y);
+        if selected.is_none() && underlined.is_empty() && background.is_none() {
+            println!("{rendered}\r");
+            return;
+        }
 
-        let line_number = if index < 10 {
-            format!("0{}", index)
-        } else {
-            format!("{}", index)
+        let set_background = || {
+            if let Some(color) = background {
+                Terminal::set_bg_color(color);
+            }
         };
+        let within = |(from, to): (usize, usize), x: usize| from <= x && x < to;
+        let mut style = (false, false);
+        let mut printed = 0;
+        set_background();
+        for (index, grapheme) in rendered.graphemes(true).enumerate() {
+            let x = start + index;
+            let wanted = (
+                selected.is_some_and(|columns| within(columns, x)),
+                underlined.iter().any(|&columns| within(columns, x)),
+            );
+            if wanted != style {
+                Terminal::reset_colors();
+                set_background();
+                if wanted.0 {
+                    Terminal::invert_colors();
+                }
+                if wanted.1 {
+                    Terminal::underline();
+                }
+                style = wanted;
+            }
+            print!("{grapheme}");
+            printed += 1;
+        }
+        if background.is_some() {
+            // Colour the rest of the line too, so conflict sections stand out.
+            Terminal::reset_colors();
+            set_background();
+            print!("{:1$}", "", width.saturating_sub(printed));
+        }
+        Terminal::reset_colors();
+        println!("\r");
+    }
 
-        // Uncomment once movement is handled
-        // Terminal::set_fg_color(LINE_NUMBER_FG_COLOR);
-        // print!("{}|", line_number);
-        // Terminal::reset_fg_color();
+    // The columns of row `y` covered by the selection, end exclusive.
+    fn selected_columns(&self, y: usize, row_len: usize) -> Option<(usize, usize)> {
+        let selection = self.selection?;
+        let (start, end) = if selection.anchor <= self.cursor_position {
+            (selection.anchor, self.cursor_position)
+        } else {
+            (self.cursor_position, selection.anchor)
+        };
+        if y < start.y || y > end.y {
+            return None;
+        }
+        if selection.mode == SelectionMode::Line {
+            return Some((0, row_len));
+        }
+        let from = if y == start.y { start.x } else { 0 };
+        let to = match selection.mode {
+            _ if y != end.y => row_len,
+            SelectionMode::Inclusive => end.x + 1,
+            _ => end.x,
+        };
 
-        println!("{}\r", row);
+        Some((from, to))
     }
 
     fn draw_status_bar(&self) {
@@ -164,8 +421,10 @@ impl<'a> Editor<'a> {
         let mut filename = String::from("[No Name]");
 
         if let Some(name) = &self.document.filename {
-            filename = name.clone();
-            filename.truncate(20);
+            filename = name.chars().take(20).collect();
+        }
+        if self.document.is_read_only() {
+            filename.push_str(" [RO]");
         }
         status = format!(
             "{} - {} lines {}",
@@ -173,21 +432,32 @@ impl<'a> Editor<'a> {
             self.document.len(),
             modified_indicator
         );
+        if !self.conflicts.is_empty() {
+            let _ = write!(status, " [{} conflict(s)]", self.conflicts.len());
+        }
+        if self.buffers.len() > 1 {
+            status = format!("[{}/{}] {status}", self.current_buffer + 1, self.buffers.len());
+        }
+        if let Some(vim) = &self.vim {
+            status = format!("[{}] {status}", vim.mode.label());
+        }
         let line_indicator = format!(
             "{}/{}",
             self.cursor_position.y.saturating_add(1),
             self.document.len()
         );
-        let len = status.len() + line_indicator.len();
+        let len = status.chars().count() + line_indicator.len();
         if width > len {
             status.push_str(&" ".repeat(width - len));
         }
-        status = format!("{}{}", status, line_indicator);
-        status.truncate(width);
+        status = format!("{status}{line_indicator}")
+            .chars()
+            .take(width)
+            .collect();
 
         Terminal::set_bg_color(self.config.status_line_bg_color);
         Terminal::set_fg_color(self.config.status_line_fg_color);
-        println!("{}\r", status);
+        println!("{status}\r");
         Terminal::reset_bg_color();
         Terminal::reset_fg_color();
     }
@@ -195,122 +465,418 @@ impl<'a> Editor<'a> {
     fn draw_message_bar(&self) {
         Terminal::clear_current_line();
         let message = &self.status_message;
-        if Instant::now() - message.time < Duration::new(5, 0) {
-            let mut text = message.text.clone();
-            text.truncate(self.terminal.size().width as usize);
-            print!("{}", text);
+        if message.time.elapsed() < Duration::new(5, 0) {
+            let width = self.terminal.size().width as usize;
+            let text: String = message.text.chars().take(width).collect();
+            print!("{text}");
         }
     }
 
-    fn draw_welcome_message(&self) {
-        let mut welcome_message = format!("Bolt editor -- version {}", VERSION);
+    // Draws the palette matches over the bottom rows of the text area, just
+    // above the status bar.
+    fn draw_palette(&self, palette: &Palette) {
         let width = self.terminal.size().width as usize;
+        let (lines, selected) = palette.lines(self.text_height().min(PALETTE_HEIGHT), width);
+        self.draw_overlay(&lines, Some(selected));
+    }
+
+    // Lays out prompt completions in columns, like a shell does.
+    fn draw_completions(&self) {
+        let width = self.terminal.size().width as usize;
+        let column = self
+            .completions
+            .iter()
+            .map(|name| name.graphemes(true).count() + 2)
+            .max()
+            .unwrap_or(1)
+            .min(width.max(1));
+        let per_line = (width / column).max(1);
+        let mut lines: Vec<String> = self
+            .completions
+            .chunks(per_line)
+            .map(|names| {
+                names.iter().fold(String::new(), |mut line, name| {
+                    let _ = write!(line, " {name:<0$}", column - 1);
+                    line
+                })
+            })
+            .collect();
+        let height = self.text_height().min(PALETTE_HEIGHT);
+        if lines.len() > height {
+            lines.truncate(height.saturating_sub(1));
+            lines.push(format!(" ... {} candidates", self.completions.len()));
+        }
+        for line in &mut lines {
+            *line = line.graphemes(true).take(width).collect();
+        }
+        self.draw_overlay(&lines, None);
+    }
+
+    // Draws `lines` over the bottom rows of the text area, just above the
+    // status bar, highlighting the `selected` one.
+    fn draw_overlay(&self, lines: &[String], selected: Option<usize>) {
+        let width = self.terminal.size().width as usize;
+        let top = self.text_height().saturating_sub(lines.len());
+
+        for (index, line) in lines.iter().enumerate() {
+            Terminal::set_cursor_position(&Position { x: 0, y: top + index });
+            Terminal::clear_current_line();
+            if selected == Some(index) {
+                Terminal::set_bg_color(self.config.status_line_bg_color);
+                Terminal::set_fg_color(self.config.status_line_fg_color);
+            }
+            print!("{line:<width$}");
+            Terminal::reset_bg_color();
+            Terminal::reset_fg_color();
+        }
+    }
+
+    fn draw_welcome_message(&self) {
+        let mut welcome_message = format!("Bolt editor -- version {VERSION}");
+        let width = self.text_width();
         let len = welcome_message.len();
         let padding = width.saturating_sub(len) / 2;
         let spaces = " ".repeat(padding.saturating_sub(1));
-        welcome_message = format!("~{}{}", spaces, welcome_message);
+        welcome_message = format!("~{spaces}{welcome_message}");
         welcome_message.truncate(width);
 
-        println!("{}\r", welcome_message);
+        println!("{welcome_message}\r");
     }
 
     fn process_keypress(&mut self) -> Result<(), std::io::Error> {
-        let pressed_key = Terminal::read_key()?;
+        let pressed_key = self.next_key()?;
+        let (buffer, y, len) = (self.current_buffer, self.cursor_position.y, self.document.len());
+        self.info.clear();
+        self.handle_key(pressed_key);
+        self.update_autocomplete();
+        if self.ends_undo_step(pressed_key) {
+            self.document.commit_edits();
+        }
+        self.lsp_sync();
+        self.update_git_gutter();
+        self.update_blame();
+        self.update_diff();
+        self.update_conflicts();
+        self.update_folds((self.current_buffer == buffer).then_some((y, len)));
+        self.show_diagnostic_at_cursor();
+        if self.document.take_refused_edit() {
+            self.set_status_message(READ_ONLY_MESSAGE);
+        }
+        self.scroll();
 
-        match pressed_key {
-            Key::Ctrl('q') => {
-                if self.document.is_dirty() && !self.confirm_quit {
-                    self.set_status_message(
-                        "Warning: File has unsaved changes. Press Ctrl + Q again to quit.",
-                    );
-                    self.confirm_quit = true;
+        Ok(())
+    }
 
-                    return Ok(());
-                }
-                self.should_quit = true
-            }
-            Key::Ctrl('s') => self.handle_save(),
-            Key::Char(c) => {
-                self.document.insert(&self.cursor_position, c);
-                self.move_cursor(Key::Right);
-            }
-            Key::Delete => self.document.delete(&self.cursor_position),
-            Key::Backspace => {
-                if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                    self.move_cursor(Key::Left);
-                    self.document.delete(&self.cursor_position);
+    // Waits for a key, meanwhile taking in what background work has found and
+    // redrawing when it changes.
+    fn next_key(&mut self) -> Result<Key, std::io::Error> {
+        loop {
+            let walking = self.finder.as_ref().is_some_and(Finder::is_walking);
+            let searching = self.grep.as_ref().is_some_and(grep::Grep::is_searching);
+            let building = self.build.as_ref().is_some_and(Build::is_running);
+            if !walking && !searching && !building && !self.lsp.is_active() {
+                return self.terminal.read_key();
+            }
+            let found = self.finder.as_mut().is_some_and(Finder::poll);
+            let received = self.poll_language_servers();
+            let built = self.poll_build();
+            if self.poll_grep() || found || received || built {
+                self.refresh_screen()?;
+            }
+            if let Some(key) = self.terminal.poll_key(POLL_INTERVAL)? {
+                return Ok(key);
+            }
+        }
+    }
+
+    // Typed words and vim insertions are undone as a whole, everything else
+    // one command at a time.
+    fn ends_undo_step(&self, key: Key) -> bool {
+        match &self.vim {
+            Some(vim) => vim.mode != Mode::Insert,
+            None => !matches!(key, Key::Char(c) if !c.is_whitespace()),
+        }
+    }
+
+    fn handle_key(&mut self, pressed_key: Key) {
+        if self.explorer_focused
+            && self.pending_keys.is_empty()
+            && self.explorer_keypress(pressed_key)
+        {
+            return;
+        }
+        if self.pending_keys.is_empty() && self.grep_keypress(pressed_key) {
+            return;
+        }
+        if self.pending_keys.is_empty() && self.replace_keypress(pressed_key) {
+            return;
+        }
+        if self.pending_keys.is_empty() && self.autocomplete_keypress(pressed_key) {
+            return;
+        }
+        if self.pending_keys.is_empty() && self.vim_keypress(pressed_key) {
+            return;
+        }
+        self.handle_mapped_key(pressed_key);
+    }
+
+    fn handle_mapped_key(&mut self, pressed_key: Key) {
+        self.pending_keys.push(pressed_key);
+
+        match self.config.keymap.lookup(&self.pending_keys) {
+            Lookup::Command(command) => {
+                self.pending_keys.clear();
+                self.run_command(command);
+            }
+            Lookup::Prefix => {
+                let chord = keymap::format_chord(&self.pending_keys);
+                self.set_status_message(&format!("{chord}-"));
+            }
+            Lookup::Unbound => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                match keys[..] {
+                    [Key::Char(_)] if self.document.is_read_only() => {
+                        self.set_status_message(READ_ONLY_MESSAGE);
+                    }
+                    [Key::Char(c)] => {
+                        self.last_command = None;
+                        self.deactivate_mark();
+                        self.document.insert(&self.cursor_position, c);
+                        self.move_cursor(Key::Right);
+                    }
+                    [_] => (),
+                    _ => {
+                        let chord = keymap::format_chord(&keys);
+                        self.set_status_message(&format!("{chord} is not bound"));
+                    }
                 }
             }
-            Key::Up
-            | Key::Down
-            | Key::Left
-            | Key::Right
-            | Key::PageDown
-            | Key::PageUp
-            | Key::Home
-            | Key::End => self.move_cursor(pressed_key),
-            _ => (),
         }
-        self.scroll();
+    }
 
-        Ok(())
+    fn run_command(&mut self, command: Command) {
+        if command != Command::Quit {
+            self.confirm_quit = false;
+        }
+        match command {
+            Command::Quit => self.handle_quit(),
+            Command::Save => self.handle_save(),
+            Command::SaveAs => self.save_as_prompt("Save as:"),
+            Command::WriteCopy => self.write_copy_prompt(),
+            Command::RenameFile => self.rename_file_prompt(),
+            Command::CommandPalette => self.open_command_palette(),
+            Command::DeleteForward => self.document.delete(&self.cursor_position),
+            Command::DeleteBackward => self.delete_backward(),
+            Command::MoveUp => self.move_cursor(Key::Up),
+            Command::MoveDown => self.move_cursor(Key::Down),
+            Command::MoveLeft => self.move_cursor(Key::Left),
+            Command::MoveRight => self.move_cursor(Key::Right),
+            Command::PageUp => self.move_cursor(Key::PageUp),
+            Command::PageDown => self.move_cursor(Key::PageDown),
+            Command::LineStart => self.cursor_position.x = 0,
+            Command::SmartLineStart => self.move_cursor(Key::Home),
+            Command::LineEnd => self.move_cursor(Key::End),
+            Command::WordForward => self.move_cursor(Key::CtrlRight),
+            Command::WordBackward => self.move_cursor(Key::CtrlLeft),
+            Command::ParagraphForward => self.move_cursor(Key::CtrlDown),
+            Command::ParagraphBackward => self.move_cursor(Key::CtrlUp),
+            Command::DocumentStart => {
+                self.jump_list.push(self.here());
+                self.move_cursor(Key::CtrlHome);
+            }
+            Command::DocumentEnd => {
+                self.jump_list.push(self.here());
+                self.move_cursor(Key::CtrlEnd);
+            }
+            Command::GotoLine => self.goto_prompt(),
+            Command::OpenFile => self.open_file_prompt(),
+            Command::FindFile => self.find_file(),
+            Command::Grep => self.grep_prompt(),
+            Command::ReplaceInFiles => self.replace_prompt(),
+            Command::UndoReplace => self.undo_replace(),
+            Command::Hover => self.hover(),
+            Command::GotoDefinition => self.goto_definition(),
+            Command::FindReferences => self.find_references(),
+            Command::Complete => self.autocomplete(),
+            Command::Build => self.run_build(),
+            Command::NextError => self.next_error(),
+            Command::PreviousError => self.previous_error(),
+            Command::BuildMessages => self.show_build_list(),
+            Command::FormatBuffer => self.format_buffer(),
+            Command::PipeThrough => self.pipe_through_prompt(),
+            Command::InsertCommandOutput => self.insert_command_output_prompt(),
+            Command::PipeToBuffer => self.pipe_to_buffer_prompt(),
+            Command::NextHunk => self.next_hunk(),
+            Command::PreviousHunk => self.previous_hunk(),
+            Command::ShowHunk => self.show_hunk(),
+            Command::RevertHunk => self.revert_hunk(),
+            Command::ToggleBlame => self.toggle_blame(),
+            Command::ShowCommit => self.show_commit(),
+            Command::DiffWithSaved => self.diff_with_saved(),
+            Command::DiffOtherSide => self.diff_other_side(),
+            Command::DiffObtain => self.diff_obtain(),
+            Command::DiffPut => self.diff_put(),
+            Command::DiffOff => self.diff_off(),
+            Command::NextConflict => self.next_conflict(),
+            Command::PreviousConflict => self.previous_conflict(),
+            Command::AcceptOurs => self.resolve_conflict(Resolution::Ours),
+            Command::AcceptTheirs => self.resolve_conflict(Resolution::Theirs),
+            Command::AcceptBoth => self.resolve_conflict(Resolution::Both),
+            Command::AcceptBase => self.resolve_conflict(Resolution::Base),
+            Command::ToggleFold => self.toggle_fold(),
+            Command::Fold => self.close_fold(),
+            Command::Unfold => self.open_fold(),
+            Command::FoldAll => self.close_all_folds(),
+            Command::UnfoldAll => self.open_all_folds(),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::ToggleExplorer => self.toggle_explorer(),
+            Command::FocusExplorer => self.focus_explorer(),
+            Command::ToggleReadOnly => self.toggle_read_only(),
+            Command::NextBuffer => self.next_buffer(),
+            Command::PreviousBuffer => self.previous_buffer(),
+            Command::JumpBack => self.jump_back(),
+            Command::JumpForward => self.jump_forward(),
+            Command::KillLine => self.kill_line(),
+            Command::KillRegion => self.kill_region(),
+            Command::CopyRegion => self.copy_region(),
+            Command::Yank => self.yank(),
+            Command::YankPop => self.yank_pop(),
+            Command::SetMark => self.set_mark(),
+            Command::Cancel => {
+                self.selection = None;
+                self.set_status_message("Quit");
+            }
+        }
+        self.last_command = Some(command);
     }
 
     fn set_status_message(&mut self, msg: &str) {
         self.status_message = StatusMessage::from(String::from(msg));
     }
 
-    fn handle_quit(&mut self) {}
+    fn handle_quit(&mut self) {
+        let unsaved = self.unsaved_buffers();
+        if unsaved > 0 && !self.confirm_quit {
+            let warning = if unsaved == 1 && self.document.is_dirty() {
+                String::from("File has unsaved changes")
+            } else {
+                format!("{unsaved} buffer(s) have unsaved changes")
+            };
+            self.set_status_message(&format!(
+                "Warning: {warning}. Press Ctrl + Q again to quit."
+            ));
+            self.confirm_quit = true;
+            return;
+        }
+        self.should_quit = true;
+    }
 
     fn handle_save(&mut self) {
-        match self.document.filename {
-            None => {
-                let new_name = self.prompt("Save as:").unwrap_or(None);
-                if new_name.is_none() {
-                    self.set_status_message("Save aborted");
-                    return;
+        if self.current_buffer == 0 && self.stdout.is_some() && self.document.filename.is_none() {
+            self.stdout = Some(self.document.contents());
+            self.document.mark_saved();
+            self.set_status_message("Buffer will be written to stdout on exit");
+            return;
+        }
+        if self.document.filename.is_none() {
+            self.save_as_prompt("Save as:");
+            return;
+        }
+
+        let format_error = self.format_before_save();
+        match self.document.save() {
+            Ok(()) => {
+                match format_error {
+                    Some(err) => {
+                        self.set_status_message(&format!("File saved, but not formatted: {err}"));
+                    }
+                    None => self.set_status_message("File saved successfully"),
                 }
+                self.lsp_did_save();
+                // Reread HEAD, which may have moved since the file was opened.
+                self.git_gutter = None;
+                self.reload_blame();
             }
-            _ => {}
+            Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                self.save_as_prompt("File is not writable. Save as:");
+            }
+            Err(err) => self.set_status_message(&format!("Error saving file: {err}")),
         }
+    }
 
-        match self.document.save() {
-            Ok(_) => self.set_status_message("File saved successfully"),
-            Err(_) => self.set_status_message("Error saving file!"),
+    fn toggle_read_only(&mut self) {
+        let read_only = !self.document.is_read_only();
+        self.document.set_read_only(read_only);
+        self.set_status_message(if read_only {
+            "Buffer is now read-only"
+        } else {
+            "Buffer is now editable"
+        });
+    }
+
+    // Checked before moving left, so a refused delete leaves the cursor be.
+    fn delete_backward(&mut self) {
+        if self.document.is_read_only() {
+            self.set_status_message(READ_ONLY_MESSAGE);
+        } else if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
+            self.move_cursor(Key::Left);
+            self.document.delete(&self.cursor_position);
         }
     }
 
-    fn prompt(&mut self, prompt: &str) -> Result<Option<String>, std::io::Error> {
-        let mut result = String::new();
-        loop {
-            self.set_status_message(&format!("{} {}", prompt, result));
-            self.refresh_screen()?;
+    fn undo(&mut self) {
+        self.document.commit_edits();
+        match self.document.undo() {
+            Some(position) => self.move_to_change(position),
+            None if !self.document.is_read_only() => {
+                self.set_status_message("Already at oldest change");
+            }
+            None => (),
+        }
+    }
 
-            match Terminal::read_key()? {
-                Key::Backspace => {
-                    if !result.is_empty() {
-                        result.truncate(result.len() - 1);
-                    }
-                }
-                Key::Char('\n') => break,
-                Key::Char(c) => {
-                    if !c.is_control() {
-                        result.push(c);
-                    }
-                }
-                Key::Esc => {
-                    result.truncate(0);
-                    break;
-                }
-                _ => (),
+    fn redo(&mut self) {
+        match self.document.redo() {
+            Some(position) => self.move_to_change(position),
+            None if !self.document.is_read_only() => {
+                self.set_status_message("Already at newest change");
             }
+            None => (),
         }
-        self.set_status_message("");
-        if result.is_empty() {
-            return Ok(None);
+    }
+
+    fn move_to_change(&mut self, position: Position) {
+        self.selection = None;
+        self.cursor_position = position;
+        self.clamp_cursor();
+    }
+
+    fn open_command_palette(&mut self) {
+        self.palette = Some(Palette::new(&self.config.keymap));
+        if let Err(err) = self.prompt_with(PromptKind::Command, "Command:", Self::update_palette) {
+            self.logger.error(&format!("Error reading command: {err}"));
         }
 
-        Ok(Some(result))
+        let palette = self.palette.take();
+        match palette.filter(|palette| palette.accepted) {
+            Some(palette) => match palette.selected_command() {
+                Some(command) => self.run_command(command),
+                None => self.set_status_message("No matching command"),
+            },
+            None => self.set_status_message(""),
+        }
+    }
+
+    fn update_palette(&mut self, query: &str, key: Key) {
+        if let Some(palette) = self.palette.as_mut() {
+            match key {
+                Key::Up => palette.select_previous(),
+                Key::Down => palette.select_next(),
+                Key::Char('\n') => palette.accepted = true,
+                _ => palette.filter(query),
+            }
+        }
     }
 
     fn move_cursor(&mut self, key: Key) {
@@ -325,17 +891,13 @@ impl<'a> Editor<'a> {
         };
 
         match key {
-            Key::Up => y = y.saturating_sub(1),
-            Key::Down => {
-                if y < document_height {
-                    y = y.saturating_add(1);
-                }
-            }
+            Key::Up => y = self.row_above(y),
+            Key::Down if y < document_height => y = self.row_below(y),
             Key::Left => {
                 if x > 0 {
                     x -= 1;
                 } else if y > 0 {
-                    y -= 1;
+                    y = self.row_above(y);
                     if let Some(row) = self.document.row(y) {
                         x = row.len();
                     } else {
@@ -347,29 +909,57 @@ impl<'a> Editor<'a> {
                 if x < row_width {
                     x += 1;
                 } else if y < document_height {
-                    y += 1;
+                    y = self.row_below(y);
                     x = 0;
                 }
             }
             Key::PageDown => {
                 y = if y.saturating_add(terminal_height) < document_height {
-                    y + terminal_height as usize
+                    y + terminal_height
                 } else {
                     0
                 }
             }
             Key::PageUp => {
-                y = if y > terminal_height {
-                    y - terminal_height
+                y = y.saturating_sub(terminal_height);
+            }
+            Key::Home => {
+                let first_non_blank = motion::first_non_blank(&self.document, y);
+                x = if x == first_non_blank { 0 } else { first_non_blank };
+            }
+            Key::End => x = row_width,
+            Key::CtrlRight => {
+                Position { x, y } = motion::next_word_end(&self.document, Position { x, y });
+            }
+            Key::CtrlLeft => {
+                Position { x, y } = motion::previous_word_start(&self.document, Position { x, y });
+            }
+            Key::CtrlDown if y + 1 < document_height => {
+                y = motion::paragraph_forward(&self.document, y + 1);
+                x = if y + 1 == document_height {
+                    self.document.row(y).map_or(0, Row::len)
                 } else {
                     0
-                }
+                };
             }
-            Key::Home => x = 0,
-            Key::End => x = row_width,
+            Key::CtrlUp => {
+                y = motion::paragraph_backward(&self.document, y.saturating_sub(1));
+                x = 0;
+            }
+            Key::CtrlHome => Position { x, y } = Position::default(),
+            Key::CtrlEnd => Position { x, y } = self.document.end_position(),
 
             _ => (),
         }
+        // Other moves into a closed fold stop on its first row, or going
+        // down, on the row past it.
+        if let Some(fold) = self.closed_fold_hiding(y) {
+            y = if y > self.cursor_position.y {
+                fold.end
+            } else {
+                fold.start
+            };
+        }
         row_width = if let Some(row) = self.document.row(y) {
             row.len()
         } else {
@@ -381,17 +971,55 @@ impl<'a> Editor<'a> {
         self.cursor_position = Position { x, y }
     }
 
+    // The number of document rows on screen, above the status bar.
+    fn text_height(&self) -> usize {
+        self.terminal.size().height.saturating_sub(1) as usize
+    }
+
+    // The screen column the document starts at, right of the sidebar.
+    fn text_left(&self) -> usize {
+        let sidebar = if self.explorer.is_some() {
+            sidebar::SIDEBAR_WIDTH
+        } else {
+            0
+        };
+        sidebar + self.gutter_width()
+    }
+
+    // The width of the text, or of each side when a diff is shown.
+    fn text_width(&self) -> usize {
+        let width = (self.terminal.size().width as usize).saturating_sub(self.text_left());
+        if self.is_diff_shown() {
+            width.saturating_sub(1) / 2
+        } else {
+            width
+        }
+    }
+
+    // Keeps the cursor on the text after it changed under it.
+    fn clamp_cursor(&mut self) {
+        let y = self.cursor_position.y.min(self.document.len());
+        let x = self
+            .cursor_position
+            .x
+            .min(self.document.row(y).map_or(0, Row::len));
+        self.cursor_position = Position { x, y };
+    }
+
     fn scroll(&mut self) {
         let Position { x, y } = self.cursor_position;
-        let width = self.terminal.size().width as usize;
-        let height = self.terminal.size().height as usize;
-        let mut offset = &mut self.offset;
-
-        if y < offset.y {
-            offset.y = y;
-        } else if y >= offset.y.saturating_add(height) {
-            offset.y = y.saturating_sub(height).saturating_add(1);
+        let width = self.text_width();
+        let height = self.text_height();
+        if let Some(fold) = self.closed_fold_hiding(self.offset.y) {
+            self.offset.y = fold.start;
         }
+        if y < self.offset.y {
+            self.offset.y = y;
+        } else if self.screen_row(y) >= height {
+            self.scroll_to_row(y, height);
+        }
+
+        let offset = &mut self.offset;
 
         if x < offset.x {
             offset.x = x;
@@ -0,0 +1,221 @@
+use super::Editor;
+use crate::autocomplete::{self, Autocomplete, Candidate, Source};
+use crate::completion;
+use crate::vim::Mode;
+use crate::{Position, Row, Terminal};
+
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+
+const POPUP_HEIGHT: usize = 8;
+const POPUP_WIDTH: usize = 60;
+
+// Whether `c` can be part of a path typed in the text.
+fn is_path_char(c: char) -> bool {
+    !c.is_whitespace() && !"\"'`()[]{}<>,;=".contains(c)
+}
+
+impl Editor<'_> {
+    // Opens the completion popup for the word before the cursor, or for the
+    // path before it when that contains a '/'.
+    pub(super) fn autocomplete(&mut self) {
+        if self
+            .vim
+            .as_ref()
+            .is_some_and(|vim| vim.mode != Mode::Insert)
+        {
+            self.set_status_message("Completion is only available in insert mode");
+            return;
+        }
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+
+        let y = self.cursor_position.y;
+        let before: Vec<&str> = self
+            .document
+            .row(y)
+            .map_or("", Row::as_str)
+            .graphemes(true)
+            .take(self.cursor_position.x)
+            .collect();
+        let start_of = |wanted: fn(char) -> bool| {
+            before
+                .iter()
+                .rposition(|grapheme| !grapheme.chars().all(wanted))
+                .map_or(0, |index| index + 1)
+        };
+        let path_start = start_of(is_path_char);
+        let path = before[path_start..].concat();
+
+        if path.contains('/') {
+            let mut popup = Autocomplete::new(Position { x: path_start, y });
+            let (directory, _) = completion::split_path(&path);
+            add_paths(&mut popup, directory);
+            self.autocomplete = Some(popup);
+        } else {
+            let mut popup = Autocomplete::new(Position {
+                x: start_of(autocomplete::is_word_char),
+                y,
+            });
+            for index in 0..self.buffers.len() {
+                let text = if index == self.current_buffer {
+                    self.document.contents()
+                } else {
+                    self.buffer_document(index).contents()
+                };
+                let words = autocomplete::words(&text);
+                popup.add(words.map(|word| Candidate::new(word.to_string(), Source::Word)));
+            }
+            self.autocomplete = Some(popup);
+            if self.lsp_document().is_some() {
+                self.lsp_sync();
+                let waiting = self.lsp_completion();
+                if let Some(popup) = self.autocomplete.as_mut() {
+                    popup.waiting = waiting;
+                }
+            }
+        }
+
+        self.update_autocomplete();
+        if self.autocomplete.is_none() {
+            self.set_status_message("No completions");
+        }
+    }
+
+    // Narrows the popup down to what has been typed since it opened, closing
+    // it once the cursor leaves the word or nothing matches.
+    pub(super) fn update_autocomplete(&mut self) {
+        let Some(popup) = &self.autocomplete else {
+            return;
+        };
+        let start = popup.start;
+        let inserting = self.vim.as_ref().is_none_or(|vim| vim.mode == Mode::Insert);
+        let cursor = self.cursor_position;
+        if !inserting || cursor.y != start.y || cursor.x < start.x {
+            self.autocomplete = None;
+            return;
+        }
+        let typed = self
+            .document
+            .row(start.y)
+            .map(|row| row.slice(start.x, cursor.x))
+            .unwrap_or_default();
+        let paths = popup.directory.is_some();
+        let valid = if paths {
+            typed.chars().all(is_path_char)
+        } else {
+            typed.chars().all(autocomplete::is_word_char)
+        };
+        if !valid {
+            self.autocomplete = None;
+            return;
+        }
+
+        let Some(popup) = self.autocomplete.as_mut() else {
+            return;
+        };
+        let (directory, _) = completion::split_path(&typed);
+        if paths && popup.directory.as_deref() != Some(directory) {
+            popup.clear();
+            add_paths(popup, directory);
+        }
+        popup.filter(&typed);
+        if popup.is_empty() && !popup.waiting {
+            self.autocomplete = None;
+        }
+    }
+
+    pub(super) fn add_lsp_completions(&mut self, items: Vec<crate::lsp::CompletionItem>) {
+        let Some(popup) = self.autocomplete.as_mut() else {
+            return;
+        };
+        popup.waiting = false;
+        popup.add(items.into_iter().map(|item| Candidate {
+            text: item.text,
+            detail: item.detail,
+            source: Source::LanguageServer,
+        }));
+        self.update_autocomplete();
+    }
+
+    // Handles the keys that move through and accept completions while the
+    // popup is open.
+    pub(super) fn autocomplete_keypress(&mut self, key: Key) -> bool {
+        let Some(popup) = self.autocomplete.as_mut() else {
+            return false;
+        };
+        match key {
+            Key::Char('\t' | '\n') => return self.accept_completion(),
+            Key::Down | Key::Ctrl('n') => popup.select_next(),
+            Key::Up | Key::Ctrl('p') => popup.select_previous(),
+            Key::Esc => self.autocomplete = None,
+            _ => return false,
+        }
+
+        true
+    }
+
+    // Replaces what was typed with the selected completion. Returns false,
+    // letting the key through, when there is nothing to accept.
+    fn accept_completion(&mut self) -> bool {
+        let Some(popup) = self.autocomplete.take() else {
+            return false;
+        };
+        let Some(candidate) = popup.selected() else {
+            return false;
+        };
+        self.document.delete_range(&popup.start, &self.cursor_position);
+        self.cursor_position = self.document.insert_str(&popup.start, &candidate.text);
+
+        true
+    }
+
+    // Draws the popup just below the start of the completed text, or above
+    // it when there is no room below.
+    pub(super) fn draw_autocomplete(&self, popup: &Autocomplete) {
+        let width = self.terminal.size().width as usize;
+        let (lines, selected) = popup.lines(POPUP_HEIGHT, POPUP_WIDTH.min(width));
+        let Some(line_width) = lines.first().map(|line| line.chars().count()) else {
+            return;
+        };
+
+        let row = self.screen_row(popup.start.y);
+        let top = if row + 1 + lines.len() <= self.text_height() {
+            row + 1
+        } else {
+            row.saturating_sub(lines.len())
+        };
+        let left = (popup.start.x.saturating_sub(self.offset.x) + self.text_left())
+            .min(width.saturating_sub(line_width));
+
+        for (index, line) in lines.iter().enumerate() {
+            Terminal::set_cursor_position(&Position {
+                x: left,
+                y: top + index,
+            });
+            if index == selected {
+                Terminal::set_bg_color(self.config.status_line_bg_color);
+                Terminal::set_fg_color(self.config.status_line_fg_color);
+            } else {
+                Terminal::invert_colors();
+            }
+            print!("{line}");
+            Terminal::reset_colors();
+            Terminal::reset_bg_color();
+            Terminal::reset_fg_color();
+        }
+    }
+}
+
+// Offers the entries of `directory`, as it was typed.
+fn add_paths(popup: &mut Autocomplete, directory: &str) {
+    let entries = completion::directory_entries(directory);
+    popup.add(
+        entries
+            .into_iter()
+            .map(|name| Candidate::new(format!("{directory}{name}"), Source::Path)),
+    );
+    popup.directory = Some(directory.to_string());
+}
@@ -0,0 +1,184 @@
+use super::gitgutter::row_texts;
+use super::{Buffer, Editor};
+use crate::diff;
+use crate::document::Version;
+use crate::git::{self, Commit};
+use crate::{Document, Position, Terminal};
+
+use std::path::Path;
+
+// Room for a short hash, a date and the start of the author's name.
+pub(super) const BLAME_WIDTH: usize = 32;
+const AUTHOR_WIDTH: usize = BLAME_WIDTH - 20;
+
+// The commits behind the lines of the file shown, as they were when blamed,
+// and for each row now which of those lines it still is.
+pub(super) struct Blame {
+    filename: Option<String>,
+    lines: Vec<String>,
+    commits: Vec<Commit>,
+    rows: Vec<Option<usize>>,
+    // The version of the document the rows were matched for.
+    version: Option<Version>,
+}
+
+impl Editor<'_> {
+    pub(super) fn toggle_blame(&mut self) {
+        if self.blame.take().is_some() {
+            self.set_status_message("Blame hidden");
+            return;
+        }
+        self.blame = Some(self.blame_document());
+        if self.has_blame() {
+            self.set_status_message("Showing blame");
+        } else {
+            self.set_status_message("The buffer's file is not tracked by git");
+        }
+    }
+
+    // Follows edits made since the buffer was blamed, and blames again when
+    // another file is shown or the file was just saved.
+    pub(super) fn update_blame(&mut self) {
+        let Some(blame) = &self.blame else {
+            return;
+        };
+        if blame.filename != self.document.filename {
+            self.blame = Some(self.blame_document());
+        }
+
+        let version = self.document.version();
+        let Some(blame) = self
+            .blame
+            .as_mut()
+            .filter(|blame| blame.version != Some(version))
+        else {
+            return;
+        };
+        let rows = row_texts(&self.document);
+        let lines: Vec<&str> = blame.lines.iter().map(String::as_str).collect();
+        blame.rows = Vec::with_capacity(rows.len());
+        let mut old = 0;
+        for hunk in diff::diff(&lines, &rows) {
+            blame.rows.extend((old..hunk.old.start).map(Some));
+            blame.rows.extend(hunk.new.map(|_| None));
+            old = hunk.old.end;
+        }
+        blame.rows.extend((old..lines.len()).map(Some));
+        blame.version = Some(version);
+    }
+
+    // Blames the file again on the next update, such as after it was saved.
+    pub(super) fn reload_blame(&mut self) {
+        if let Some(blame) = self.blame.as_mut() {
+            blame.filename = None;
+        }
+    }
+
+    pub(super) fn has_blame(&self) -> bool {
+        self.blame
+            .as_ref()
+            .is_some_and(|blame| !blame.commits.is_empty())
+    }
+
+    pub(super) fn draw_blame(&self, y: usize) {
+        let annotation = match self.blamed_commit(y) {
+            Some(commit) if commit.is_committed() => format!(
+                "{} {} {:<AUTHOR_WIDTH$.AUTHOR_WIDTH$} ",
+                commit.short_hash(),
+                commit.date(),
+                commit.author
+            ),
+            Some(_) => String::from("Not committed yet"),
+            None => String::new(),
+        };
+        Terminal::set_fg_color(termion::color::Rgb(147, 161, 161));
+        print!("{annotation:<BLAME_WIDTH$}");
+        Terminal::reset_fg_color();
+    }
+
+    // Opens the full message of the commit that last touched the cursor's
+    // line in a read-only buffer.
+    pub(super) fn show_commit(&mut self) {
+        let Some(filename) = self.document.filename.clone() else {
+            self.set_status_message("The buffer has no file to blame");
+            return;
+        };
+        let y = self.cursor_position.y;
+        let commit = match self.blamed_commit(y) {
+            Some(commit) => Some(commit.clone()),
+            None if !self.has_blame() => {
+                git::blame(Path::new(&filename), &self.document.contents())
+                    .and_then(|mut commits| (y < commits.len()).then(|| commits.swap_remove(y)))
+            }
+            None => None,
+        };
+        let Some(commit) = commit else {
+            self.set_status_message("The buffer's file is not tracked by git");
+            return;
+        };
+        if !commit.is_committed() {
+            self.set_status_message("This line is not committed yet");
+            return;
+        }
+        let Some(message) = git::commit_message(Path::new(&filename), &commit.hash) else {
+            self.set_status_message(&format!("Could not read commit {}", commit.short_hash()));
+            return;
+        };
+
+        let mut document = Document::from_text(&message);
+        document.set_read_only(true);
+        let buffer = Buffer::new(document, Position::default());
+        // The previous commit's buffer is replaced, unless it has since been
+        // saved somewhere or edited.
+        let copy = self.commit_copy.filter(|&index| {
+            let document = self.buffer_document(index);
+            index != self.current_buffer && document.filename.is_none() && !document.is_dirty()
+        });
+        let copy = if let Some(index) = copy {
+            self.buffers[index] = buffer;
+            index
+        } else {
+            self.buffers.push(buffer);
+            self.buffers.len() - 1
+        };
+        self.commit_copy = Some(copy);
+        self.switch_to_buffer(copy);
+        self.set_status_message(&format!("Commit {}", commit.short_hash()));
+    }
+
+    fn blame_document(&self) -> Blame {
+        let commits = self
+            .document
+            .filename
+            .as_ref()
+            .and_then(|filename| git::blame(Path::new(filename), &self.document.contents()))
+            .unwrap_or_default();
+        let lines = row_texts(&self.document)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        Blame {
+            filename: self.document.filename.clone(),
+            lines,
+            commits,
+            rows: Vec::new(),
+            version: None,
+        }
+    }
+
+    fn blamed_commit(&self, y: usize) -> Option<&Commit> {
+        let blame = self.blame.as_ref()?;
+        match blame.rows.get(y)? {
+            Some(line) => blame.commits.get(*line),
+            // Rows edited since the blame are not committed.
+            None => Some(&UNCOMMITTED),
+        }
+    }
+}
+
+static UNCOMMITTED: Commit = Commit {
+    hash: String::new(),
+    author: String::new(),
+    time: 0,
+    offset: 0,
+};
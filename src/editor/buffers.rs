@@ -0,0 +1,196 @@
+use super::prompt::PromptKind;
+use super::Editor;
+use crate::jumplist::Jump;
+use crate::{Document, Position};
+
+use std::path::Path;
+
+#[derive(Default)]
+pub struct Buffer {
+    pub(super) document: Document,
+    cursor_position: Position,
+    offset: Position,
+    closed_folds: Vec<usize>,
+}
+
+impl Buffer {
+    pub fn new(document: Document, cursor_position: Position) -> Self {
+        Self {
+            document,
+            cursor_position,
+            ..Self::default()
+        }
+    }
+}
+
+impl Editor<'_> {
+    // Shows buffer `index`, remembering the place left on the jump list.
+    pub(super) fn switch_to_buffer(&mut self, index: usize) {
+        if index == self.current_buffer || index >= self.buffers.len() {
+            return;
+        }
+        self.jump_list.push(self.here());
+        self.change_buffer(index);
+    }
+
+    // The active buffer lives in the editor's own fields; its slot in
+    // `buffers` only holds a placeholder until another buffer is shown.
+    pub(super) fn change_buffer(&mut self, index: usize) {
+        if index == self.current_buffer || index >= self.buffers.len() {
+            return;
+        }
+        self.document.commit_edits();
+        self.buffers[self.current_buffer] = Buffer {
+            document: std::mem::take(&mut self.document),
+            cursor_position: self.cursor_position,
+            offset: self.offset,
+            closed_folds: std::mem::take(&mut self.closed_folds),
+        };
+        self.show_buffer(index);
+    }
+
+    // Moves buffer `index` into the editor, discarding whatever is shown.
+    pub(super) fn show_buffer(&mut self, index: usize) {
+        let buffer = std::mem::take(&mut self.buffers[index]);
+        self.document = buffer.document;
+        self.cursor_position = buffer.cursor_position;
+        self.offset = buffer.offset;
+        self.closed_folds = buffer.closed_folds;
+        self.current_buffer = index;
+        self.selection = None;
+        self.autocomplete = None;
+        self.pending_keys.clear();
+        // The text may have been changed from elsewhere while it was hidden.
+        self.clamp_cursor();
+        if self.cursor_position.y >= self.offset.y + self.text_height() {
+            self.center_on_cursor();
+        }
+    }
+
+    pub(super) fn next_buffer(&mut self) {
+        if self.buffers.len() < 2 {
+            self.set_status_message("No other buffers");
+            return;
+        }
+        self.switch_to_buffer((self.current_buffer + 1) % self.buffers.len());
+    }
+
+    pub(super) fn previous_buffer(&mut self) {
+        if self.buffers.len() < 2 {
+            self.set_status_message("No other buffers");
+            return;
+        }
+        let count = self.buffers.len();
+        self.switch_to_buffer((self.current_buffer + count - 1) % count);
+    }
+
+    pub(super) fn open_file_prompt(&mut self) {
+        if let Ok(Some(path)) = self.prompt(PromptKind::Path, "Open file:") {
+            if let Err(err) = self.open_file(path.trim()) {
+                self.set_status_message(&format!("Could not open {}: {err}", path.trim()));
+            }
+        }
+    }
+
+    // Shows `path`, switching to its buffer if it is already open.
+    pub(super) fn open_file(&mut self, path: &str) -> Result<(), std::io::Error> {
+        if let Some(index) = self.buffer_index(path) {
+            self.switch_to_buffer(index);
+            return Ok(());
+        }
+        let document = Document::open_or_new(path)?;
+        self.buffers
+            .push(Buffer::new(document, Position::default()));
+        self.switch_to_buffer(self.buffers.len() - 1);
+
+        Ok(())
+    }
+
+    pub(super) fn buffer_index(&self, path: &str) -> Option<usize> {
+        (0..self.buffers.len()).find(|&index| {
+            self.buffer_document(index)
+                .filename
+                .as_ref()
+                .is_some_and(|filename| same_file(filename, path))
+        })
+    }
+
+    // Replaces the current buffer with `path`, unless it is open elsewhere.
+    pub(super) fn open_file_here(&mut self, path: &str) -> Result<(), std::io::Error> {
+        if let Some(index) = self.buffer_index(path) {
+            self.switch_to_buffer(index);
+            return Ok(());
+        }
+        if self.document.is_dirty() && !self.confirm("Discard unsaved changes?") {
+            return Ok(());
+        }
+        self.buffers[self.current_buffer] =
+            Buffer::new(Document::open_or_new(path)?, Position::default());
+        self.show_buffer(self.current_buffer);
+
+        Ok(())
+    }
+
+    // Follows a file that was renamed on disk in every buffer showing it.
+    pub(super) fn rename_buffers(&mut self, old: &str, new: &str) {
+        let current = self.current_buffer;
+        let documents = self
+            .buffers
+            .iter_mut()
+            .enumerate()
+            .filter(|(index, _)| *index != current)
+            .map(|(_, buffer)| &mut buffer.document)
+            .chain(std::iter::once(&mut self.document));
+        for document in documents {
+            let renamed = document.filename.as_ref().is_some_and(|filename| {
+                std::path::absolute(filename).ok() == std::path::absolute(old).ok()
+            });
+            if renamed {
+                document.filename = Some(new.to_string());
+            }
+        }
+    }
+
+    pub(super) fn here(&self) -> Jump {
+        Jump {
+            buffer: self.current_buffer,
+            position: self.cursor_position,
+        }
+    }
+
+    pub(super) fn buffer_document(&self, index: usize) -> &Document {
+        if index == self.current_buffer {
+            &self.document
+        } else {
+            &self.buffers[index].document
+        }
+    }
+
+    pub(super) fn buffer_document_mut(&mut self, index: usize) -> &mut Document {
+        if index == self.current_buffer {
+            &mut self.document
+        } else {
+            &mut self.buffers[index].document
+        }
+    }
+
+    pub(super) fn unsaved_buffers(&self) -> usize {
+        let others = self
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(index, buffer)| *index != self.current_buffer && buffer.document.is_dirty())
+            .count();
+
+        others + usize::from(self.document.is_dirty())
+    }
+}
+
+pub(super) fn same_file(open: &str, wanted: &str) -> bool {
+    let open = Path::new(open);
+    let wanted = Path::new(wanted);
+    match (open.canonicalize(), wanted.canonicalize()) {
+        (Ok(open), Ok(wanted)) => open == wanted,
+        _ => open.ends_with(wanted) || wanted.ends_with(open),
+    }
+}
@@ -0,0 +1,187 @@
+use super::sidebar::display_path;
+use super::Editor;
+use crate::build::{Build, BuildMessage};
+use crate::lsp::Severity;
+use crate::project;
+use crate::{Position, Row};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+
+impl Editor<'_> {
+    // Runs the configured build command in the background, replacing any
+    // build still running.
+    pub(super) fn run_build(&mut self) {
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let root = project::workspace_root(&cwd);
+        let command = self.config.build_command.clone();
+        self.build = None;
+        self.build_index = None;
+        match Build::start(&command, &root) {
+            Ok(build) => {
+                self.build = Some(build);
+                self.set_status_message(&format!("Running {command}..."));
+            }
+            Err(err) => self.set_status_message(&format!("Could not run {command}: {err}")),
+        }
+    }
+
+    // Takes in the build's output, returning whether the screen needs
+    // redrawing.
+    pub(super) fn poll_build(&mut self) -> bool {
+        let Some(build) = self.build.as_mut().filter(|build| build.is_running()) else {
+            return false;
+        };
+        let (found, status) = build.poll();
+        let Some(status) = status else {
+            return found;
+        };
+
+        let errors = build.count(Severity::Error);
+        let warnings = build.count(Severity::Warning);
+        let outcome = if status.success() {
+            String::from("finished")
+        } else {
+            match status.code() {
+                Some(code) => format!("failed with exit code {code}"),
+                None => String::from("was killed"),
+            }
+        };
+        let message = format!(
+            "{} {outcome}: {errors} error(s), {warnings} warning(s)",
+            build.command
+        );
+        self.set_status_message(&message);
+
+        true
+    }
+
+    pub(super) fn next_error(&mut self) {
+        let count = self.build_messages().len();
+        let next = self.build_index.map_or(0, |index| index + 1);
+        if next >= count {
+            self.set_status_message("No more build messages");
+            return;
+        }
+        self.show_build_message(next);
+    }
+
+    pub(super) fn previous_error(&mut self) {
+        let Some(previous) = self.build_index.and_then(|index| index.checked_sub(1)) else {
+            self.set_status_message("No previous build messages");
+            return;
+        };
+        self.show_build_message(previous);
+    }
+
+    fn build_messages(&self) -> &[BuildMessage] {
+        self.build
+            .as_ref()
+            .map_or(&[], |build| build.messages.as_slice())
+    }
+
+    // Opens the file of message `index` at its position, putting the message
+    // in the message bar.
+    fn show_build_message(&mut self, index: usize) {
+        let Some(message) = self.build_messages().get(index) else {
+            return;
+        };
+        let path = display_path(&message.path);
+        let (line, column) = (message.line, message.column);
+        let status = format!(
+            "[{}/{}] {}: {}",
+            index + 1,
+            self.build_messages().len(),
+            message.severity.label(),
+            message.message
+        );
+        self.build_index = Some(index);
+        let opened = self.jump_to_file(&path, |document| {
+            let y = line.min(document.len().saturating_sub(1));
+            let row = document.row(y).map_or("", Row::as_str);
+            Position {
+                x: grapheme_column(row, column),
+                y,
+            }
+        });
+        if opened {
+            self.set_status_message(&status);
+        }
+    }
+
+    // Lists the build's messages in the results buffer.
+    pub(super) fn show_build_list(&mut self) {
+        // Files that are not open are read once, to find their rows in.
+        let mut files: HashMap<&Path, String> = HashMap::new();
+        let results: Vec<(PathBuf, Position, String)> = self
+            .build_messages()
+            .iter()
+            .map(|message| {
+                let path = message.path.to_string_lossy();
+                let row = match self.buffer_index(&path) {
+                    Some(index) => self
+                        .buffer_document(index)
+                        .row(message.line)
+                        .map_or("", Row::as_str),
+                    None => files
+                        .entry(&message.path)
+                        .or_insert_with(|| fs::read_to_string(&message.path).unwrap_or_default())
+                        .lines()
+                        .nth(message.line)
+                        .unwrap_or(""),
+                };
+                let position = Position {
+                    x: grapheme_column(row, message.column),
+                    y: message.line,
+                };
+                let text = format!("{}: {}", message.severity.label(), message.message);
+                (message.path.clone(), position, text)
+            })
+            .collect();
+        if results.is_empty() {
+            self.set_status_message("No build messages");
+            return;
+        }
+        let title = format!("{} build message(s)", results.len());
+        self.show_results(&title, results);
+    }
+
+    // The build messages about the current document.
+    fn document_build_messages(&self) -> impl Iterator<Item = &BuildMessage> {
+        let messages = self.build_messages();
+        let path = if messages.is_empty() {
+            None
+        } else {
+            self.document.canonical_path()
+        };
+        messages
+            .iter()
+            .filter(move |message| path.as_ref().is_some_and(|path| *path == message.path))
+    }
+
+    pub(super) fn has_build_markers(&self) -> bool {
+        self.document_build_messages().next().is_some()
+    }
+
+    // The most severe build message on row `y` of the current document.
+    pub(super) fn build_severity(&self, y: usize) -> Option<Severity> {
+        self.document_build_messages()
+            .filter(|message| message.line == y)
+            .map(|message| message.severity)
+            .min()
+    }
+}
+
+// The grapheme at character `column` of `line`.
+fn grapheme_column(line: &str, column: usize) -> usize {
+    let mut characters = 0;
+    line.graphemes(true)
+        .take_while(|grapheme| {
+            characters += grapheme.chars().count();
+            characters <= column
+        })
+        .count()
+}
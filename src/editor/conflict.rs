@@ -0,0 +1,122 @@
+use super::Editor;
+use crate::conflict::{self, Conflict};
+use crate::Position;
+
+use termion::color::Rgb;
+
+const MARKER_COLOR: Rgb = Rgb(72, 72, 72);
+const OURS_COLOR: Rgb = Rgb(32, 64, 32);
+const BASE_COLOR: Rgb = Rgb(48, 48, 48);
+const THEIRS_COLOR: Rgb = Rgb(32, 48, 80);
+
+#[derive(Clone, Copy)]
+pub(super) enum Resolution {
+    Ours,
+    Theirs,
+    Both,
+    Base,
+}
+
+impl Editor<'_> {
+    // Finds the conflicts again once the text has changed.
+    pub(super) fn update_conflicts(&mut self) {
+        let version = self.document.version();
+        if self.conflicts_version != Some(version) {
+            self.conflicts = conflict::find(&self.document);
+            self.conflicts_version = Some(version);
+        }
+    }
+
+    // The background of row `y` when it is part of a conflict.
+    pub(super) fn conflict_color(&self, y: usize) -> Option<Rgb> {
+        let conflict = self.conflict_at(y)?;
+        Some(if conflict.is_marker(y) {
+            MARKER_COLOR
+        } else if conflict.ours.contains(&y) {
+            OURS_COLOR
+        } else if conflict.theirs.contains(&y) {
+            THEIRS_COLOR
+        } else {
+            BASE_COLOR
+        })
+    }
+
+    pub(super) fn next_conflict(&mut self) {
+        let y = self.cursor_position.y;
+        let Some(start) = self
+            .conflicts
+            .iter()
+            .map(|c| c.start)
+            .find(|&start| start > y)
+        else {
+            self.set_status_message("No more conflicts");
+            return;
+        };
+        self.jump_to(Position { x: 0, y: start });
+    }
+
+    pub(super) fn previous_conflict(&mut self) {
+        let y = self.cursor_position.y;
+        let Some(start) = self
+            .conflicts
+            .iter()
+            .map(|c| c.start)
+            .rev()
+            .find(|&start| start < y)
+        else {
+            self.set_status_message("No previous conflicts");
+            return;
+        };
+        self.jump_to(Position { x: 0, y: start });
+    }
+
+    // Replaces the conflict under the cursor, markers included, with the
+    // chosen side, as one undo step.
+    pub(super) fn resolve_conflict(&mut self, resolution: Resolution) {
+        let Some(conflict) = self.conflict_at(self.cursor_position.y).cloned() else {
+            self.set_status_message("No conflict under the cursor");
+            return;
+        };
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let sections = match resolution {
+            Resolution::Ours => vec![conflict.ours.clone()],
+            Resolution::Theirs => vec![conflict.theirs.clone()],
+            Resolution::Both => vec![conflict.ours.clone(), conflict.theirs.clone()],
+            Resolution::Base => {
+                let Some(base) = conflict.base.clone() else {
+                    self.set_status_message("This conflict has no base version");
+                    return;
+                };
+                vec![base]
+            }
+        };
+        let text = sections
+            .into_iter()
+            .flatten()
+            .filter_map(|y| self.document.row(y))
+            .fold(String::new(), |mut text, row| {
+                text.push_str(row.as_str());
+                text.push('\n');
+                text
+            });
+
+        self.document.commit_edits();
+        self.document
+            .replace_lines(conflict.start..conflict.end + 1, &text);
+        self.document.commit_edits();
+        self.cursor_position = Position {
+            x: 0,
+            y: conflict.start,
+        };
+        self.clamp_cursor();
+        self.update_conflicts();
+        self.set_status_message(&format!("Conflict resolved, {} left", self.conflicts.len()));
+    }
+
+    fn conflict_at(&self, y: usize) -> Option<&Conflict> {
+        self.conflicts.iter().find(|conflict| conflict.contains(y))
+    }
+}
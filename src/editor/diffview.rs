@@ -0,0 +1,413 @@
+use super::gitgutter::{hunk_at, row_texts};
+use super::{Buffer, Editor};
+use crate::diff::{self, Hunk};
+use crate::document::Version;
+use crate::{Document, Position, Row, Terminal};
+
+use std::fs;
+use std::ops::Range;
+use termion::color::Rgb;
+use unicode_segmentation::UnicodeSegmentation;
+
+const REMOVED_COLOR: Rgb = Rgb(72, 32, 32);
+const ADDED_COLOR: Rgb = Rgb(32, 64, 32);
+const CHANGED_COLOR: Rgb = Rgb(40, 48, 72);
+const CHANGED_TEXT_COLOR: Rgb = Rgb(88, 96, 150);
+const FILLER_COLOR: Rgb = Rgb(88, 110, 117);
+
+// A row of each side shown next to each other. A side without a row is
+// filler, standing in for lines only the other side has.
+struct DiffLine {
+    left: Option<usize>,
+    right: Option<usize>,
+    changed: bool,
+}
+
+// Two buffers compared side by side. The one being edited is the current
+// buffer, the other is kept in the buffer list.
+pub(super) struct DiffView {
+    left: usize,
+    right: usize,
+    // `old` ranges are rows of the left buffer, `new` ranges of the right.
+    hunks: Vec<Hunk>,
+    lines: Vec<DiffLine>,
+    // The versions of the left and right documents the lines were found for.
+    versions: Option<(Version, Version)>,
+    // The first of `lines` on screen.
+    top: usize,
+}
+
+impl Editor<'_> {
+    // Compares buffers `left` and `right`, moving to `right` unless one of
+    // them is already shown.
+    pub fn show_diff(&mut self, left: usize, right: usize) {
+        if left == right || left.max(right) >= self.buffers.len() {
+            return;
+        }
+        if self.current_buffer != left {
+            self.switch_to_buffer(right);
+        }
+        self.diff = Some(DiffView {
+            left,
+            right,
+            hunks: Vec::new(),
+            lines: Vec::new(),
+            versions: None,
+            top: 0,
+        });
+        self.update_diff();
+        let count = self.diff.as_ref().map_or(0, |view| view.hunks.len());
+        self.set_status_message(&format!("{count} change(s)"));
+    }
+
+    // Compares the buffer with its file as last saved.
+    pub(super) fn diff_with_saved(&mut self) {
+        let Some(filename) = self.document.filename.clone() else {
+            self.set_status_message("The buffer has no file to compare with");
+            return;
+        };
+        let text = match fs::read_to_string(&filename) {
+            Ok(text) => text,
+            Err(err) => {
+                self.set_status_message(&format!("Could not read {filename}: {err}"));
+                return;
+            }
+        };
+        let mut document = Document::from_text(&text);
+        document.set_read_only(true);
+        let buffer = Buffer::new(document, self.cursor_position);
+        // The copy from last time is replaced, unless it has since been
+        // saved somewhere or edited.
+        let copy = self.saved_copy.filter(|&index| {
+            let document = self.buffer_document(index);
+            index != self.current_buffer && document.filename.is_none() && !document.is_dirty()
+        });
+        let copy = if let Some(index) = copy {
+            self.buffers[index] = buffer;
+            index
+        } else {
+            self.buffers.push(buffer);
+            self.buffers.len() - 1
+        };
+        self.saved_copy = Some(copy);
+        self.show_diff(copy, self.current_buffer);
+    }
+
+    pub(super) fn diff_off(&mut self) {
+        if self.diff.take().is_some() {
+            self.set_status_message("Diff closed");
+        }
+    }
+
+    // Compares the sides again after edits, and keeps the cursor's line on
+    // screen. The view closes when another buffer is shown.
+    pub(super) fn update_diff(&mut self) {
+        let Some(view) = &self.diff else {
+            return;
+        };
+        if self.current_buffer != view.left && self.current_buffer != view.right {
+            self.diff = None;
+            return;
+        }
+
+        let (left, right) = (
+            self.side_document(view.left),
+            self.side_document(view.right),
+        );
+        let versions = (left.version(), right.version());
+        if view.versions != Some(versions) {
+            let left_rows = row_texts(left);
+            let right_rows = row_texts(right);
+            let hunks = diff::diff(&left_rows, &right_rows);
+            let lines = align(&hunks, left_rows.len(), right_rows.len());
+            if let Some(view) = self.diff.as_mut() {
+                view.hunks = hunks;
+                view.lines = lines;
+                view.versions = Some(versions);
+            }
+        }
+
+        let height = self.text_height();
+        let cursor = self.diff.as_ref().map_or(0, |view| {
+            self.diff_line(&view.lines, self.cursor_position.y)
+        });
+        if let Some(view) = self.diff.as_mut() {
+            if cursor < view.top {
+                view.top = cursor;
+            } else if cursor >= view.top + height {
+                view.top = cursor + 1 - height;
+            }
+        }
+    }
+
+    pub(super) fn is_diff_shown(&self) -> bool {
+        self.diff.is_some()
+    }
+
+    // The changes of the diff, with `new` on the side being edited and `old`
+    // on the other.
+    pub(super) fn diff_hunks(&self) -> Option<Vec<Hunk>> {
+        let view = self.diff.as_ref()?;
+        let on_left = self.current_buffer == view.left;
+        Some(
+            view.hunks
+                .iter()
+                .map(|hunk| {
+                    if on_left {
+                        Hunk {
+                            old: hunk.new.clone(),
+                            new: hunk.old.clone(),
+                        }
+                    } else {
+                        hunk.clone()
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    // Moves to the other side, onto the row next to the cursor's.
+    pub(super) fn diff_other_side(&mut self) {
+        let Some(view) = &self.diff else {
+            self.set_status_message("No diff is shown");
+            return;
+        };
+        let on_left = self.current_buffer == view.left;
+        let other = if on_left { view.right } else { view.left };
+        let line = self.diff_line(&view.lines, self.cursor_position.y);
+        let side = |line: &DiffLine| if on_left { line.right } else { line.left };
+        let y = view.lines[line..]
+            .iter()
+            .find_map(side)
+            .or_else(|| view.lines[..line].iter().rev().find_map(side))
+            .unwrap_or(0);
+
+        let offset = self.offset;
+        let x = self.cursor_position.x;
+        self.switch_to_buffer(other);
+        self.offset = offset;
+        self.cursor_position = Position { x, y };
+        self.clamp_cursor();
+        self.update_diff();
+    }
+
+    // Replaces the change under the cursor with the other side's version.
+    pub(super) fn diff_obtain(&mut self) {
+        let Some((hunk, other)) = self.diff_hunk_at_cursor() else {
+            return;
+        };
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let text = lines_text(self.side_document(other), hunk.old);
+        self.document.commit_edits();
+        self.document.replace_lines(hunk.new.clone(), &text);
+        self.document.commit_edits();
+        self.cursor_position = Position {
+            x: 0,
+            y: hunk.new.start,
+        };
+        self.clamp_cursor();
+        self.update_diff();
+    }
+
+    // Replaces the other side's version of the change under the cursor
+    // with this one.
+    pub(super) fn diff_put(&mut self) {
+        let Some((hunk, other)) = self.diff_hunk_at_cursor() else {
+            return;
+        };
+        let text = lines_text(&self.document, hunk.new);
+        let document = &mut self.buffers[other].document;
+        if document.is_read_only() {
+            self.set_status_message("The other side is read-only");
+            return;
+        }
+        document.commit_edits();
+        document.replace_lines(hunk.old, &text);
+        document.commit_edits();
+        self.update_diff();
+    }
+
+    pub(super) fn draw_diff_line(&self, row_index: usize) {
+        let Some(view) = &self.diff else {
+            return;
+        };
+        let Some(line) = view.lines.get(view.top + row_index) else {
+            let width = self.text_width();
+            println!("{:<width$} ~\r", "~");
+            return;
+        };
+        let left = line.left.and_then(|y| self.side_document(view.left).row(y));
+        let right = line
+            .right
+            .and_then(|y| self.side_document(view.right).row(y));
+        let (left_changes, right_changes) = match (left, right) {
+            (Some(left), Some(right)) if line.changed => changed_columns(left, right),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let background = |color| match (left, right) {
+            _ if !line.changed => None,
+            (Some(_), Some(_)) => Some(CHANGED_COLOR),
+            _ => Some(color),
+        };
+
+        self.draw_diff_pane(left, background(REMOVED_COLOR), &left_changes);
+        Terminal::set_fg_color(FILLER_COLOR);
+        print!("│");
+        Terminal::reset_fg_color();
+        self.draw_diff_pane(right, background(ADDED_COLOR), &right_changes);
+        println!("\r");
+    }
+
+    fn draw_diff_pane(&self, row: Option<&Row>, background: Option<Rgb>, changes: &[Range<usize>]) {
+        let width = self.text_width();
+        let Some(row) = row else {
+            Terminal::set_fg_color(FILLER_COLOR);
+            print!("{}", "-".repeat(width));
+            Terminal::reset_fg_color();
+            return;
+        };
+
+        // The changes are columns of the row's text, so the row is shown a
+        // grapheme of its text at a time.
+        let mut color = None;
+        let mut printed = 0;
+        for (column, grapheme) in row.as_str().graphemes(true).enumerate().skip(self.offset.x) {
+            let shown = Row::render_grapheme(grapheme);
+            let cells = shown.graphemes(true).count();
+            if printed + cells > width {
+                break;
+            }
+            let wanted = if changes.iter().any(|range| range.contains(&column)) {
+                Some(CHANGED_TEXT_COLOR)
+            } else {
+                background
+            };
+            if wanted != color {
+                match wanted {
+                    Some(wanted) => Terminal::set_bg_color(wanted),
+                    None => Terminal::reset_bg_color(),
+                }
+                color = wanted;
+            }
+            print!("{shown}");
+            printed += cells;
+        }
+        match background {
+            Some(background) => Terminal::set_bg_color(background),
+            None => Terminal::reset_bg_color(),
+        }
+        print!("{:1$}", "", width.saturating_sub(printed));
+        Terminal::reset_bg_color();
+    }
+
+    // Where the cursor is on screen, when a diff is shown.
+    pub(super) fn diff_cursor(&self) -> Option<Position> {
+        let view = self.diff.as_ref()?;
+        let pane = if self.current_buffer == view.left {
+            0
+        } else {
+            self.text_width() + 1
+        };
+        let line = self.diff_line(&view.lines, self.cursor_position.y);
+        Some(Position {
+            x: self.cursor_position.x.saturating_sub(self.offset.x) + self.text_left() + pane,
+            y: line.saturating_sub(view.top),
+        })
+    }
+
+    // The change under the cursor, with the index of the other side's buffer.
+    fn diff_hunk_at_cursor(&mut self) -> Option<(Hunk, usize)> {
+        let Some(hunks) = self.diff_hunks() else {
+            self.set_status_message("No diff is shown");
+            return None;
+        };
+        let Some(hunk) = hunk_at(&hunks, self.cursor_position.y) else {
+            self.set_status_message("No change on this line");
+            return None;
+        };
+        let view = self.diff.as_ref()?;
+        let other = if self.current_buffer == view.left {
+            view.right
+        } else {
+            view.left
+        };
+        Some((hunk, other))
+    }
+
+    // Which of `lines` shows row `y` of the current buffer.
+    fn diff_line(&self, lines: &[DiffLine], y: usize) -> usize {
+        let on_left = self
+            .diff
+            .as_ref()
+            .is_some_and(|view| self.current_buffer == view.left);
+        lines
+            .iter()
+            .position(|line| if on_left { line.left } else { line.right } == Some(y))
+            .unwrap_or(lines.len())
+    }
+
+    fn side_document(&self, index: usize) -> &Document {
+        if index == self.current_buffer {
+            &self.document
+        } else {
+            &self.buffers[index].document
+        }
+    }
+}
+
+// Pairs up the rows of both sides: unchanged rows next to each other, and
+// changed ones next to each other as far as both sides have them, then next
+// to filler.
+fn align(hunks: &[Hunk], left_len: usize, right_len: usize) -> Vec<DiffLine> {
+    let mut lines = Vec::new();
+    let (mut left, mut right) = (0, 0);
+    let ends = Hunk {
+        old: left_len..left_len,
+        new: right_len..right_len,
+    };
+    for hunk in hunks.iter().chain([&ends]) {
+        while left < hunk.old.start {
+            lines.push(DiffLine {
+                left: Some(left),
+                right: Some(right),
+                changed: false,
+            });
+            left += 1;
+            right += 1;
+        }
+        for index in 0..hunk.old.len().max(hunk.new.len()) {
+            lines.push(DiffLine {
+                left: (index < hunk.old.len()).then_some(hunk.old.start + index),
+                right: (index < hunk.new.len()).then_some(hunk.new.start + index),
+                changed: true,
+            });
+        }
+        left = hunk.old.end;
+        right = hunk.new.end;
+    }
+
+    lines
+}
+
+// The columns that differ between two rows shown next to each other.
+fn changed_columns(left: &Row, right: &Row) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let left: Vec<&str> = left.as_str().graphemes(true).collect();
+    let right: Vec<&str> = right.as_str().graphemes(true).collect();
+    diff::diff(&left, &right)
+        .into_iter()
+        .map(|hunk| (hunk.old, hunk.new))
+        .unzip()
+}
+
+fn lines_text(document: &Document, range: Range<usize>) -> String {
+    range
+        .filter_map(|y| document.row(y))
+        .fold(String::new(), |mut text, row| {
+            text.push_str(row.as_str());
+            text.push('\n');
+            text
+        })
+}
@@ -0,0 +1,123 @@
+use super::buffers::same_file;
+use super::prompt::PromptKind;
+use super::Editor;
+
+use std::fs;
+use std::path::Path;
+use termion::event::Key;
+
+impl Editor<'_> {
+    pub(super) fn save_as_prompt(&mut self, prompt: &str) {
+        match self.prompt(PromptKind::Path, prompt) {
+            Ok(Some(path)) => self.save_as(path.trim()),
+            _ => self.set_status_message("Save aborted"),
+        }
+    }
+
+    pub(super) fn save_as(&mut self, path: &str) {
+        let Some(path) = self.prepare_destination(path) else {
+            return;
+        };
+        let old_name = self.document.filename.replace(path.clone());
+        match self.document.save() {
+            Ok(()) => self.set_status_message(&format!("Saved as {path}")),
+            Err(err) => {
+                self.document.filename = old_name;
+                self.set_status_message(&format!("Error saving file: {err}"));
+            }
+        }
+    }
+
+    pub(super) fn write_copy_prompt(&mut self) {
+        if let Ok(Some(path)) = self.prompt(PromptKind::Path, "Write a copy to:") {
+            self.write_copy(path.trim());
+        }
+    }
+
+    pub(super) fn write_copy(&mut self, path: &str) {
+        let Some(path) = self.prepare_destination(path) else {
+            return;
+        };
+        match self.document.write_to(&path) {
+            Ok(()) => self.set_status_message(&format!("Wrote a copy to {path}")),
+            Err(err) => self.set_status_message(&format!("Error writing {path}: {err}")),
+        }
+    }
+
+    pub(super) fn rename_file_prompt(&mut self) {
+        let Some(old_name) = self.document.filename.clone() else {
+            self.set_status_message("The buffer has no file to rename; use save-as");
+            return;
+        };
+        let Ok(Some(path)) = self.prompt_prefilled(PromptKind::Path, "Rename to:", &old_name) else {
+            return;
+        };
+        let Some(path) = self.prepare_destination(path.trim()) else {
+            return;
+        };
+        match fs::rename(&old_name, &path) {
+            Ok(()) => {
+                self.set_status_message(&format!("Renamed {old_name} to {path}"));
+                self.rename_buffers(&old_name, &path);
+            }
+            Err(err) => self.set_status_message(&format!("Error renaming {old_name}: {err}")),
+        }
+    }
+
+    // Checks that `path` can be written, asking before overwriting another
+    // file or creating missing directories. Relative paths are taken from the
+    // directory bolt was started in.
+    pub(super) fn prepare_destination(&mut self, path: &str) -> Option<String> {
+        if path.is_empty() {
+            self.set_status_message("No file name given");
+            return None;
+        }
+        let target = Path::new(path);
+        if target.is_dir() {
+            self.set_status_message(&format!("{path} is a directory"));
+            return None;
+        }
+        let is_current = self
+            .document
+            .filename
+            .as_ref()
+            .is_some_and(|filename| same_file(filename, path));
+        if target.exists() && !is_current && !self.confirm(&format!("Overwrite {path}?")) {
+            self.set_status_message("Cancelled");
+            return None;
+        }
+        if let Some(parent) = target
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty() && !parent.exists())
+        {
+            let parent_name = parent.display().to_string();
+            if !self.confirm(&format!("Create directory {parent_name}?")) {
+                self.set_status_message("Cancelled");
+                return None;
+            }
+            if let Err(err) = fs::create_dir_all(parent) {
+                self.set_status_message(&format!("Could not create {parent_name}: {err}"));
+                return None;
+            }
+        }
+
+        Some(path.to_string())
+    }
+
+    pub(super) fn confirm(&mut self, question: &str) -> bool {
+        self.set_status_message(&format!("{question} (y/n)"));
+        let answer = loop {
+            if self.refresh_screen().is_err() {
+                break false;
+            }
+            match self.terminal.read_key() {
+                Ok(Key::Char('y' | 'Y')) => break true,
+                Ok(Key::Char('n' | 'N') | Key::Esc | Key::Ctrl('g')) | Err(_) => break false,
+                Ok(_) => (),
+            }
+        };
+        self.set_status_message("");
+
+        answer
+    }
+}
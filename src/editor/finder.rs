@@ -0,0 +1,103 @@
+use super::prompt::PromptKind;
+use super::sidebar::display_path;
+use super::Editor;
+use crate::finder::Finder;
+use crate::project;
+use crate::{Position, Terminal};
+
+use std::env;
+use std::path::{Path, PathBuf};
+use termion::event::Key;
+
+impl Editor<'_> {
+    // Picks a file from the project the current file belongs to, or the one
+    // around the working directory, and opens it in a new buffer.
+    pub(super) fn find_file(&mut self) {
+        let start = self
+            .document
+            .filename
+            .as_ref()
+            .and_then(|filename| std::path::absolute(filename).ok())
+            .and_then(|file| file.parent().map(Path::to_path_buf))
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let root = project::project_root(&start);
+        self.finder = Some(Finder::new(root.clone(), project::walk_files(&root)));
+        if let Err(err) = self.prompt_with(PromptKind::Command, "Find file:", Self::update_finder) {
+            self.logger.error(&format!("Error reading file name: {err}"));
+        }
+
+        let finder = self.finder.take();
+        let Some(finder) = finder.filter(|finder| finder.accepted) else {
+            self.set_status_message("");
+            return;
+        };
+        let Some(path) = finder.selected_path() else {
+            self.set_status_message("No matching file");
+            return;
+        };
+        let path = display_path(&path);
+        if let Err(err) = self.open_file(&path) {
+            self.set_status_message(&format!("Could not open {path}: {err}"));
+        }
+    }
+
+    fn update_finder(&mut self, query: &str, key: Key) {
+        if let Some(finder) = self.finder.as_mut() {
+            match key {
+                Key::Up | Key::Ctrl('p') => finder.select_previous(),
+                Key::Down | Key::Ctrl('n') => finder.select_next(),
+                Key::Char('\n') => finder.accepted = true,
+                _ => finder.filter(query),
+            }
+        }
+    }
+
+    // Lists the matches over the lower half of the text area, the matched
+    // characters in bold, under a line counting them.
+    pub(super) fn draw_finder(&self, finder: &Finder) {
+        let width = self.terminal.size().width as usize;
+        let height = (self.text_height() / 2).max(2);
+        let (lines, selected) = finder.lines(height - 1);
+        let top = self.text_height().saturating_sub(lines.len() + 1);
+
+        Terminal::set_cursor_position(&Position { x: 0, y: top });
+        Terminal::clear_current_line();
+        Terminal::invert_colors();
+        let summary = format!(" {}", finder.summary());
+        print!("{summary:<width$}");
+        Terminal::reset_colors();
+        for (index, (path, positions)) in lines.into_iter().enumerate() {
+            Terminal::set_cursor_position(&Position {
+                x: 0,
+                y: top + 1 + index,
+            });
+            Terminal::clear_current_line();
+            let highlighted = index == selected;
+            self.set_finder_colors(highlighted);
+            print!(" ");
+            let mut printed = 1;
+            for (index, c) in path.chars().enumerate().take(width.saturating_sub(1)) {
+                if positions.contains(&index) {
+                    Terminal::bold();
+                    print!("{c}");
+                    Terminal::reset_colors();
+                    self.set_finder_colors(highlighted);
+                } else {
+                    print!("{c}");
+                }
+                printed += 1;
+            }
+            print!("{}", " ".repeat(width.saturating_sub(printed)));
+            Terminal::reset_bg_color();
+            Terminal::reset_fg_color();
+        }
+    }
+
+    fn set_finder_colors(&self, highlighted: bool) {
+        if highlighted {
+            Terminal::set_bg_color(self.config.status_line_bg_color);
+            Terminal::set_fg_color(self.config.status_line_fg_color);
+        }
+    }
+}
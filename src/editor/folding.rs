@@ -0,0 +1,204 @@
+use super::Editor;
+use crate::fold::{self, Fold};
+use crate::{Row, Terminal};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+const SUMMARY_COLOR: termion::color::Rgb = termion::color::Rgb(147, 161, 161);
+
+impl Editor<'_> {
+    // Finds the folds again once the text has changed. `edit` is the row
+    // edits may have been made at, with the length of the document before:
+    // closed folds below it move with the rows added or removed there. They
+    // stay closed while a fold still starts on their row.
+    pub(super) fn update_folds(&mut self, edit: Option<(usize, usize)>) {
+        let version = self.document.version();
+        if self.folds_version != Some(version) {
+            let ranges = self.lsp_folding_ranges();
+            self.folds = fold::folds(&self.document, &ranges);
+            if let Some((y, old_len)) = edit {
+                let len = self.document.len();
+                for start in &mut self.closed_folds {
+                    if *start > y {
+                        *start = (*start + len).saturating_sub(old_len);
+                    }
+                }
+            }
+            let folds = &self.folds;
+            self.closed_folds
+                .retain(|start| folds.iter().any(|fold| fold.start == *start));
+            self.folds_version = Some(version);
+        }
+
+        // A jump or a search can land inside a closed fold, which then opens.
+        while let Some(fold) = self.closed_fold_hiding(self.cursor_position.y) {
+            self.closed_folds.retain(|&start| start != fold.start);
+        }
+    }
+
+    // Closes the innermost open fold around the cursor, or opens the closed
+    // one it is on.
+    pub(super) fn toggle_fold(&mut self) {
+        if self.closed_fold_at(self.cursor_position.y).is_some() {
+            self.open_fold();
+        } else {
+            self.close_fold();
+        }
+    }
+
+    pub(super) fn close_fold(&mut self) {
+        let y = self.cursor_position.y;
+        let Some(fold) = self
+            .folds
+            .iter()
+            .filter(|fold| fold.start <= y && y < fold.end)
+            .filter(|fold| !self.closed_folds.contains(&fold.start))
+            .min_by_key(|fold| fold.end - fold.start)
+            .copied()
+        else {
+            self.set_status_message("No fold here");
+            return;
+        };
+        self.closed_folds.push(fold.start);
+        self.cursor_position.y = fold.start;
+        self.clamp_cursor();
+    }
+
+    pub(super) fn open_fold(&mut self) {
+        let Some(fold) = self.closed_fold_at(self.cursor_position.y) else {
+            self.set_status_message("No closed fold here");
+            return;
+        };
+        self.closed_folds.retain(|&start| start != fold.start);
+    }
+
+    pub(super) fn close_all_folds(&mut self) {
+        self.closed_folds = self.folds.iter().map(|fold| fold.start).collect();
+        // The cursor moves up to the outermost fold hiding it.
+        if let Some(fold) = self
+            .folds
+            .iter()
+            .find(|fold| fold.hides(self.cursor_position.y))
+        {
+            self.cursor_position.y = fold.start;
+            self.clamp_cursor();
+        }
+    }
+
+    pub(super) fn open_all_folds(&mut self) {
+        self.closed_folds.clear();
+    }
+
+    // The outermost closed fold that hides row `y`.
+    pub(super) fn closed_fold_hiding(&self, y: usize) -> Option<Fold> {
+        self.folds
+            .iter()
+            .find(|fold| fold.hides(y) && self.closed_folds.contains(&fold.start))
+            .copied()
+    }
+
+    // The closed fold shown as a summary on row `y`, unless a closed fold
+    // around it hides that row.
+    pub(super) fn closed_fold_at(&self, y: usize) -> Option<Fold> {
+        if self.closed_fold_hiding(y).is_some() {
+            return None;
+        }
+        self.folds
+            .iter()
+            .filter(|fold| fold.start == y && self.closed_folds.contains(&fold.start))
+            .max_by_key(|fold| fold.end)
+            .copied()
+    }
+
+    // The row shown after row `y`, past a closed fold starting there.
+    pub(super) fn row_below(&self, y: usize) -> usize {
+        self.closed_fold_at(y).map_or(y + 1, |fold| fold.end)
+    }
+
+    // The row shown before row `y`, or the summary of a fold hiding it.
+    pub(super) fn row_above(&self, y: usize) -> usize {
+        let above = y.saturating_sub(1);
+        self.closed_fold_hiding(above)
+            .map_or(above, |fold| fold.start)
+    }
+
+    // The row shown `count` rows above row `y`, or the first row.
+    pub(super) fn rows_above(&self, y: usize, count: usize) -> usize {
+        (0..count).fold(y, |y, _| self.row_above(y))
+    }
+
+    // The row shown `count` rows below row `y`, or the last one shown.
+    pub(super) fn rows_below(&self, y: usize, count: usize) -> usize {
+        let last_row = self.document.len().saturating_sub(1);
+        let mut y = y;
+        for _ in 0..count {
+            let below = self.row_below(y);
+            if below > last_row {
+                break;
+            }
+            y = below;
+        }
+        y
+    }
+
+    // How many rows are shown from the top of the screen down to row `y`,
+    // counting no further than the bottom of the screen.
+    pub(super) fn screen_row(&self, y: usize) -> usize {
+        if self.closed_folds.is_empty() {
+            return y.saturating_sub(self.offset.y);
+        }
+        let height = self.text_height();
+        let mut row = self.offset.y;
+        let mut count = 0;
+        while row < y && count < height {
+            row = self.row_below(row);
+            count += 1;
+        }
+        count
+    }
+
+    // Moves the top of the screen down until row `y` is on it.
+    pub(super) fn scroll_to_row(&mut self, y: usize, height: usize) {
+        let mut top = y;
+        for _ in 1..height {
+            if top == 0 {
+                break;
+            }
+            top = self.row_above(top);
+        }
+        self.offset.y = top;
+    }
+
+    pub(super) fn has_closed_folds(&self) -> bool {
+        !self.closed_folds.is_empty()
+    }
+
+    pub(super) fn draw_fold_mark(&self, y: usize) {
+        let mark = if self.closed_fold_at(y).is_some() {
+            "▸"
+        } else if self.folds.iter().any(|fold| fold.start == y) {
+            "▾"
+        } else {
+            " "
+        };
+        Terminal::set_fg_color(SUMMARY_COLOR);
+        print!("{mark}");
+        Terminal::reset_fg_color();
+    }
+
+    // Draws a closed fold as its first row followed by how much it hides.
+    pub(super) fn render_fold(&self, row: &Row, fold: Fold) {
+        let width = self.text_width();
+        let rendered = row.render(self.offset.x, self.offset.x + width);
+        let shown = rendered.graphemes(true).count();
+        let summary: String = format!(" ··· {} lines", fold.hidden())
+            .graphemes(true)
+            .take(width.saturating_sub(shown))
+            .collect();
+        print!("{rendered}");
+        Terminal::set_fg_color(SUMMARY_COLOR);
+        print!("{summary}");
+        Terminal::reset_fg_color();
+        println!("\r");
+    }
+}
@@ -0,0 +1,121 @@
+use super::Editor;
+use crate::pipe;
+use crate::Position;
+
+use std::path::Path;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Editor<'_> {
+    pub(super) fn format_buffer(&mut self) {
+        match self.format_document() {
+            Ok(true) => self.set_status_message("Buffer formatted"),
+            Ok(false) => self.set_status_message("Buffer is already formatted"),
+            Err(err) => self.set_status_message(&err),
+        }
+    }
+
+    // Formats the buffer if its formatter runs on save, returning why that
+    // failed.
+    pub(super) fn format_before_save(&mut self) -> Option<String> {
+        let filename = self.document.filename.as_ref()?;
+        if !self.config.formatter(filename)?.on_save {
+            return None;
+        }
+        self.format_document().err()
+    }
+
+    // Replaces the text with the formatter's output as a single undo step.
+    // Returns whether it changed anything.
+    fn format_document(&mut self) -> Result<bool, String> {
+        let filename = self
+            .document
+            .filename
+            .clone()
+            .ok_or("The buffer has no file name to pick a formatter by")?;
+        let formatter = self
+            .config
+            .formatter(&filename)
+            .ok_or_else(|| format!("No formatter is configured for {filename}"))?;
+        if self.document.is_read_only() {
+            return Err(String::from(super::READ_ONLY_MESSAGE));
+        }
+        let command = formatter.command.clone();
+        let args: Vec<String> = formatter
+            .args
+            .iter()
+            .map(|arg| arg.replace("{file}", &filename))
+            .collect();
+
+        let text = self.document.contents();
+        let output = pipe::run(&command, &args, Path::new("."), &text, FORMAT_TIMEOUT)
+            .map_err(|err| format!("Could not run {command}: {err}"))?;
+        if !output.status.success() {
+            return Err(format!("{command} failed: {}", output.error_message()));
+        }
+        let result = output.stdout;
+        if result.lines().eq(text.lines()) {
+            return Ok(false);
+        }
+        // A formatter that rewrites the file in place prints nothing, and
+        // taking that at its word would empty the buffer.
+        if result.trim().is_empty() && !text.trim().is_empty() {
+            return Err(format!("{command} printed nothing"));
+        }
+
+        let cursor = follow_line(&text, &result, self.cursor_position);
+        self.document.commit_edits();
+        self.document.set_contents(&result);
+        self.document.commit_edits();
+        self.cursor_position = cursor;
+        self.clamp_cursor();
+
+        Ok(true)
+    }
+}
+
+// Where the cursor at `cursor` in `old` ends up in `new`: on the same line
+// when it is unchanged or was only reindented, and otherwise near where the
+// changed lines were.
+fn follow_line(old: &str, new: &str, cursor: Position) -> Position {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let shortest = old.len().min(new.len());
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(shortest - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let y = cursor.y;
+    let new_y = if y < prefix {
+        y
+    } else if y >= old.len() - suffix {
+        y + new.len() - old.len()
+    } else {
+        let changed = prefix..new.len() - suffix;
+        let guess = (y - prefix + changed.start).min(changed.end.saturating_sub(1));
+        let line = old[y].trim();
+        changed
+            .filter(|&index| new[index].trim() == line)
+            .min_by_key(|&index| index.abs_diff(guess))
+            .unwrap_or(guess)
+    };
+
+    let indent = |line: &str| {
+        line.graphemes(true)
+            .take_while(|g| g.trim().is_empty())
+            .count()
+    };
+    let old_indent = old.get(y).map_or(0, |line| indent(line));
+    let new_indent = new.get(new_y).map_or(0, |line| indent(line));
+    Position {
+        x: cursor.x.saturating_sub(old_indent) + new_indent,
+        y: new_y,
+    }
+}
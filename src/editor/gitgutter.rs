@@ -0,0 +1,218 @@
+use super::Editor;
+use crate::diff::{self, Hunk};
+use crate::document::Version;
+use crate::git;
+use crate::{Document, Position, Row};
+
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum LineChange {
+    Added,
+    Changed,
+    // Lines were deleted below this row, or above it on the first row.
+    Deleted,
+}
+
+// The current file as committed at HEAD, and how the buffer differs from it.
+pub(super) struct GitGutter {
+    filename: String,
+    head: Option<Vec<String>>,
+    hunks: Vec<Hunk>,
+    // The version of the document the hunks were found for.
+    version: Option<Version>,
+}
+
+impl Editor<'_> {
+    // Compares the buffer with HEAD, reading the committed text again when the
+    // buffer shows another file or was just saved. The comparison is only made
+    // again once the text has changed.
+    pub(super) fn update_git_gutter(&mut self) {
+        let Some(filename) = self.document.filename.clone() else {
+            self.git_gutter = None;
+            return;
+        };
+        if self
+            .git_gutter
+            .as_ref()
+            .is_none_or(|gutter| gutter.filename != filename)
+        {
+            let head = git::head_text(Path::new(&filename))
+                .map(|text| text.lines().map(String::from).collect());
+            self.git_gutter = Some(GitGutter {
+                filename,
+                head,
+                hunks: Vec::new(),
+                version: None,
+            });
+        }
+
+        let version = self.document.version();
+        let Some(gutter) = self
+            .git_gutter
+            .as_mut()
+            .filter(|gutter| gutter.version != Some(version))
+        else {
+            return;
+        };
+        let rows = row_texts(&self.document);
+        gutter.hunks = gutter.head.as_ref().map_or_else(Vec::new, |head| {
+            let head: Vec<&str> = head.iter().map(String::as_str).collect();
+            diff::diff(&head, &rows)
+        });
+        gutter.version = Some(version);
+    }
+
+    pub(super) fn has_git_gutter(&self) -> bool {
+        self.git_gutter
+            .as_ref()
+            .is_some_and(|gutter| gutter.head.is_some())
+    }
+
+    pub(super) fn git_change(&self, y: usize) -> Option<LineChange> {
+        let hunks = self.git_hunks();
+        if let Some(hunk) = hunks.iter().find(|hunk| hunk.new.contains(&y)) {
+            return Some(if hunk.old.is_empty() {
+                LineChange::Added
+            } else {
+                LineChange::Changed
+            });
+        }
+        hunks
+            .iter()
+            .any(|hunk| hunk.new.is_empty() && hunk_row(hunk) == y)
+            .then_some(LineChange::Deleted)
+    }
+
+    // Moves to the next change against the other side of a diff, or else
+    // against HEAD.
+    pub(super) fn next_hunk(&mut self) {
+        let y = self.cursor_position.y;
+        let hunks = self.change_hunks();
+        let Some(row) = hunks.iter().map(hunk_row).find(|&row| row > y) else {
+            self.set_status_message(self.no_hunks_message("No more changes"));
+            return;
+        };
+        self.jump_to(Position { x: 0, y: row });
+    }
+
+    pub(super) fn previous_hunk(&mut self) {
+        let y = self.cursor_position.y;
+        let hunks = self.change_hunks();
+        let Some(row) = hunks.iter().map(hunk_row).rev().find(|&row| row < y) else {
+            self.set_status_message(self.no_hunks_message("No previous changes"));
+            return;
+        };
+        self.jump_to(Position { x: 0, y: row });
+    }
+
+    // Shows the hunk under the cursor as a unified diff against HEAD.
+    pub(super) fn show_hunk(&mut self) {
+        let Some(hunk) = self.hunk_at_cursor() else {
+            self.set_status_message(self.no_hunks_message("No change on this line"));
+            return;
+        };
+        let mut lines = vec![format!(
+            "@@ -{},{} +{},{} @@",
+            hunk.old.start + 1,
+            hunk.old.len(),
+            hunk.new.start + 1,
+            hunk.new.len()
+        )];
+        lines.extend(self.head_lines(&hunk).iter().map(|line| format!("-{line}")));
+        lines.extend(
+            hunk.new
+                .clone()
+                .filter_map(|y| self.document.row(y))
+                .map(|row| format!("+{}", row.as_str())),
+        );
+        self.info = lines;
+    }
+
+    // Puts the lines of the hunk under the cursor back as they are at HEAD.
+    pub(super) fn revert_hunk(&mut self) {
+        let Some(hunk) = self.hunk_at_cursor() else {
+            self.set_status_message(self.no_hunks_message("No change on this line"));
+            return;
+        };
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let text = self
+            .head_lines(&hunk)
+            .iter()
+            .fold(String::new(), |mut text, line| {
+                text.push_str(line);
+                text.push('\n');
+                text
+            });
+        self.document.commit_edits();
+        self.document.replace_lines(hunk.new.clone(), &text);
+        self.document.commit_edits();
+        self.cursor_position = Position {
+            x: 0,
+            y: hunk.new.start,
+        };
+        self.clamp_cursor();
+        self.update_git_gutter();
+        self.set_status_message("Change reverted");
+    }
+
+    fn git_hunks(&self) -> &[Hunk] {
+        self.git_gutter
+            .as_ref()
+            .map_or(&[], |gutter| gutter.hunks.as_slice())
+    }
+
+    fn change_hunks(&self) -> Vec<Hunk> {
+        self.diff_hunks()
+            .unwrap_or_else(|| self.git_hunks().to_vec())
+    }
+
+    fn hunk_at_cursor(&self) -> Option<Hunk> {
+        hunk_at(self.git_hunks(), self.cursor_position.y)
+    }
+
+    fn head_lines(&self, hunk: &Hunk) -> &[String] {
+        self.git_gutter
+            .as_ref()
+            .and_then(|gutter| gutter.head.as_ref())
+            .and_then(|head| head.get(hunk.old.clone()))
+            .unwrap_or_default()
+    }
+
+    fn no_hunks_message(&self, message: &'static str) -> &'static str {
+        if self.is_diff_shown() || self.has_git_gutter() {
+            message
+        } else {
+            "The buffer's file is not tracked by git"
+        }
+    }
+}
+
+// The row a hunk is marked on: its first line, or for deleted lines the row
+// above where they were.
+fn hunk_row(hunk: &Hunk) -> usize {
+    if hunk.new.is_empty() {
+        hunk.new.start.saturating_sub(1)
+    } else {
+        hunk.new.start
+    }
+}
+
+// The hunk marked on row `y`.
+pub(super) fn hunk_at(hunks: &[Hunk], y: usize) -> Option<Hunk> {
+    hunks
+        .iter()
+        .find(|hunk| hunk.new.contains(&y) || (hunk.new.is_empty() && hunk_row(hunk) == y))
+        .cloned()
+}
+
+// The text of every row, for comparing with other versions of it.
+pub(super) fn row_texts(document: &Document) -> Vec<&str> {
+    (0..document.len())
+        .filter_map(|y| document.row(y))
+        .map(Row::as_str)
+        .collect()
+}
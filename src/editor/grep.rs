@@ -0,0 +1,183 @@
+use super::prompt::PromptKind;
+use super::sidebar::display_path;
+use super::{Buffer, Editor};
+use crate::grep::{self, GrepMatch};
+use crate::project;
+use crate::{Document, Position};
+
+use regex::Regex;
+use std::env;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use termion::event::Key;
+
+// How many matches to list per poll, so a search matching everything does
+// not hold up the keyboard.
+const POLL_BATCH: usize = 1000;
+
+// The latest search, listed one match per row in its own read-only buffer.
+pub(super) struct Grep {
+    buffer: usize,
+    pattern: String,
+    matches: Vec<(String, Position)>,
+    search: Option<Receiver<GrepMatch>>,
+}
+
+impl Grep {
+    pub(super) fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+}
+
+impl Editor<'_> {
+    pub(super) fn grep_prompt(&mut self) {
+        if let Ok(Some(pattern)) = self.prompt(PromptKind::Search, "Search project (regex):") {
+            self.grep(&pattern);
+        }
+    }
+
+    // Starts searching the project around the working directory for
+    // `pattern`, showing the results buffer as it fills.
+    pub(super) fn grep(&mut self, pattern: &str) {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.set_status_message(&format!("Invalid regex: {err}"));
+                return;
+            }
+        };
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let root = project::project_root(&cwd);
+        self.open_results(pattern, Some(grep::search(&root, regex)));
+        self.set_status_message(&format!("Searching for {pattern}..."));
+    }
+
+    // Lists locations found some other way, such as a language server's
+    // references, in the results buffer.
+    pub(super) fn show_results(&mut self, title: &str, results: Vec<(PathBuf, Position, String)>) {
+        self.open_results(title, None);
+        let Some(grep) = self.grep.as_mut() else {
+            return;
+        };
+        let mut lines = Vec::new();
+        for (path, position, text) in results {
+            let path = display_path(&path);
+            lines.push(result_line(&path, position, &text));
+            grep.matches.push((path, position));
+        }
+        let document = self.buffer_document_mut(self.current_buffer);
+        for line in &lines {
+            document.append_line(line);
+        }
+        self.set_status_message(title);
+    }
+
+    // Shows an empty results buffer. A new search reuses the previous one's
+    // buffer.
+    fn open_results(&mut self, title: &str, search: Option<Receiver<GrepMatch>>) {
+        let mut document = Document::from_text("");
+        document.set_read_only(true);
+        let buffer = Buffer::new(document, Position::default());
+        let index = if let Some(grep) = &self.grep {
+            self.buffers[grep.buffer] = buffer;
+            grep.buffer
+        } else {
+            self.buffers.push(buffer);
+            self.buffers.len() - 1
+        };
+        if index == self.current_buffer {
+            self.show_buffer(index);
+        } else {
+            self.switch_to_buffer(index);
+        }
+        self.grep = Some(Grep {
+            buffer: index,
+            pattern: title.to_string(),
+            matches: Vec::new(),
+            search,
+        });
+    }
+
+    // Lists the matches found since the last call, returning whether there
+    // was anything new to show.
+    pub(super) fn poll_grep(&mut self) -> bool {
+        let Some(grep) = self.grep.as_mut() else {
+            return false;
+        };
+        let Some(search) = &grep.search else {
+            return false;
+        };
+        let mut lines = Vec::new();
+        let mut finished = false;
+        while lines.len() < POLL_BATCH {
+            match search.try_recv() {
+                Ok(found) => {
+                    let path = display_path(&found.path);
+                    let position = Position {
+                        x: found.column,
+                        y: found.line,
+                    };
+                    lines.push(result_line(&path, position, &found.text));
+                    grep.matches.push((path, position));
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        let count = grep.matches.len();
+        let message = if finished {
+            grep.search = None;
+            format!("{count} match(es) for {}", grep.pattern)
+        } else {
+            format!("Searching for {}... {count} match(es)", grep.pattern)
+        };
+        let buffer = grep.buffer;
+        let document = self.buffer_document_mut(buffer);
+        for line in &lines {
+            document.append_line(line);
+        }
+        self.set_status_message(&message);
+
+        finished || !lines.is_empty()
+    }
+
+    // In the results buffer, Enter opens the match under the cursor and Esc
+    // stops a search that is still running.
+    pub(super) fn grep_keypress(&mut self, key: Key) -> bool {
+        let Some(grep) = self.grep.as_mut() else {
+            return false;
+        };
+        if grep.buffer != self.current_buffer {
+            return false;
+        }
+        match key {
+            Key::Char('\n') => {
+                let Some((path, position)) = grep.matches.get(self.cursor_position.y).cloned()
+                else {
+                    return true;
+                };
+                self.jump_to_file(&path, |document| {
+                    let y = position.y.min(document.len().saturating_sub(1));
+                    let x = position.x.min(document.row(y).map_or(0, crate::Row::len));
+                    Position { x, y }
+                });
+            }
+            Key::Esc if grep.is_searching() => {
+                grep.search = None;
+                let message = format!("Search cancelled after {} match(es)", grep.matches.len());
+                self.set_status_message(&message);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+// "path:line:column: text", counting from one.
+fn result_line(path: &str, position: Position, text: &str) -> String {
+    format!("{path}:{}:{}: {text}", position.y + 1, position.x + 1)
+}
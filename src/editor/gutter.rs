@@ -0,0 +1,69 @@
+use super::blame::BLAME_WIDTH;
+use super::gitgutter::LineChange;
+use super::Editor;
+use crate::lsp::Severity;
+use crate::Terminal;
+
+impl Editor<'_> {
+    // Room left of the text for a diagnostic mark and a git change mark, in
+    // buffers that have a language server, build messages or a tracked file,
+    // for blame when it is shown, and for fold marks while a fold is closed.
+    // A diff leaves no room for any of them.
+    pub(super) fn gutter_width(&self) -> usize {
+        if self.is_diff_shown() {
+            return 0;
+        }
+        let marks = if self.has_marks() { 2 } else { 0 };
+        let blame = if self.has_blame() { BLAME_WIDTH } else { 0 };
+        let folds = usize::from(self.has_closed_folds());
+        marks + blame + folds
+    }
+
+    pub(super) fn draw_gutter(&self, y: usize) {
+        if self.has_marks() {
+            self.draw_marks(y);
+        }
+        if self.has_blame() {
+            self.draw_blame(y);
+        }
+        if self.has_closed_folds() {
+            self.draw_fold_mark(y);
+        }
+    }
+
+    fn has_marks(&self) -> bool {
+        self.lsp_document().is_some() || self.has_build_markers() || self.has_git_gutter()
+    }
+
+    fn draw_marks(&self, y: usize) {
+        let severity = self
+            .current_diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.start.line == y)
+            .map(|diagnostic| diagnostic.severity)
+            .chain(self.build_severity(y))
+            .min();
+        let diagnostic = severity.map(|severity| match severity {
+            Severity::Error => ('E', termion::color::Rgb(220, 50, 47)),
+            Severity::Warning => ('W', termion::color::Rgb(181, 137, 0)),
+            Severity::Information => ('I', termion::color::Rgb(38, 139, 210)),
+            Severity::Hint => ('H', termion::color::Rgb(147, 161, 161)),
+        });
+        let change = self.git_change(y).map(|change| match change {
+            LineChange::Added => ('+', termion::color::Rgb(133, 153, 0)),
+            LineChange::Changed => ('~', termion::color::Rgb(181, 137, 0)),
+            LineChange::Deleted => ('-', termion::color::Rgb(220, 50, 47)),
+        });
+
+        for mark in [diagnostic, change] {
+            match mark {
+                Some((mark, color)) => {
+                    Terminal::set_fg_color(color);
+                    print!("{mark}");
+                    Terminal::reset_fg_color();
+                }
+                None => print!(" "),
+            }
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use super::Editor;
+use crate::lsp::{self, Diagnostic, LanguageServer, LspPosition, Message};
+use crate::project;
+use crate::{Position, Row};
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// How long typing has to pause before the folding ranges are asked for again.
+const FOLDING_DELAY: Duration = Duration::from_millis(300);
+
+// What a request sent to a language server was for.
+#[derive(Clone)]
+enum Pending {
+    Hover,
+    Definition,
+    References,
+    Completion,
+    // The folding ranges of the document with this uri.
+    FoldingRange(String),
+}
+
+#[derive(Default)]
+pub(super) struct LspState {
+    // Running servers, by language id.
+    servers: HashMap<String, LanguageServer>,
+    // Languages whose server could not be started, so it is not retried on
+    // every key press.
+    failed: HashSet<String>,
+    diagnostics: HashMap<String, Vec<Diagnostic>>,
+    pending: HashMap<(String, u64), Pending>,
+    // The first and last lines of each region that can be folded, by uri.
+    folding: HashMap<String, Vec<(usize, usize)>>,
+    // When each document whose folding ranges are out of date was last
+    // changed, by language id and uri.
+    folding_due: HashMap<(String, String), Instant>,
+    // The diagnostic message last shown for the cursor's line.
+    shown: Option<String>,
+}
+
+impl LspState {
+    pub(super) fn is_active(&self) -> bool {
+        !self.servers.is_empty()
+    }
+}
+
+impl Editor<'_> {
+    // The language id and uri of the current document, when it has a server
+    // listening to it.
+    pub(super) fn lsp_document(&self) -> Option<(String, String)> {
+        let filename = self.document.filename.as_ref()?;
+        let (language, _) = self.config.language_server(filename)?;
+        let uri = lsp::path_to_uri(Path::new(filename));
+        let server = self.lsp.servers.get(language)?;
+        server.is_open(&uri).then(|| (language.to_string(), uri))
+    }
+
+    // Starts servers for newly opened files and tells them about every edit
+    // since the last call. Servers still starting up are told once they are
+    // ready.
+    pub(super) fn lsp_sync(&mut self) {
+        for index in 0..self.buffers.len() {
+            let Some(filename) = self.buffer_document(index).filename.clone() else {
+                continue;
+            };
+            let Some((language, config)) = self.config.language_server(&filename) else {
+                continue;
+            };
+            let language = language.to_string();
+            if !self.lsp.servers.contains_key(&language) && !self.lsp.failed.contains(&language) {
+                let start = std::path::absolute(&filename)
+                    .ok()
+                    .and_then(|path| path.parent().map(Path::to_path_buf))
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let root = project::project_root(&start);
+                match LanguageServer::start(config, &root) {
+                    Ok(server) => {
+                        self.lsp.servers.insert(language.clone(), server);
+                    }
+                    Err(err) => {
+                        self.lsp.failed.insert(language.clone());
+                        let message = format!("Could not start {}: {err}", config.command);
+                        self.set_status_message(&message);
+                        continue;
+                    }
+                }
+            }
+
+            let uri = lsp::path_to_uri(Path::new(&filename));
+            let document = if index == self.current_buffer {
+                &mut self.document
+            } else {
+                &mut self.buffers[index].document
+            };
+            let Some(server) = self
+                .lsp
+                .servers
+                .get_mut(&language)
+                .filter(|server| server.is_ready())
+            else {
+                continue;
+            };
+            let sent = if server.is_open(&uri) && document.tracks_changes() {
+                let changes = document.take_changes();
+                if changes.is_empty() {
+                    continue;
+                }
+                server.did_change(&uri, &changes, &document.contents())
+            } else {
+                // A document that replaced the one the server has open, as a
+                // reload does, is opened afresh.
+                let closed = if server.is_open(&uri) {
+                    server.did_close(&uri)
+                } else {
+                    Ok(())
+                };
+                document.track_changes();
+                document.take_changes();
+                closed.and_then(|()| server.did_open(&uri, &language, &document.contents()))
+            };
+            if server.provides_folding_ranges() {
+                self.lsp
+                    .folding_due
+                    .insert((language.clone(), uri), Instant::now());
+            }
+            if let Err(err) = sent {
+                self.logger
+                    .error(&format!("Error talking to the {language} server: {err}"));
+            }
+        }
+        self.lsp_close_hidden();
+    }
+
+    // Closes the documents the servers have open that no buffer shows any
+    // more, as after opening another file in a buffer's place.
+    fn lsp_close_hidden(&mut self) {
+        let shown: HashSet<String> = (0..self.buffers.len())
+            .filter_map(|index| self.buffer_document(index).filename.as_ref())
+            .map(|filename| lsp::path_to_uri(Path::new(filename)))
+            .collect();
+        for server in self.lsp.servers.values_mut() {
+            for uri in server.open_documents() {
+                if shown.contains(&uri) {
+                    continue;
+                }
+                if let Err(err) = server.did_close(&uri) {
+                    self.logger.error(&format!("Error closing {uri}: {err}"));
+                }
+                self.lsp.diagnostics.remove(&uri);
+                self.lsp.folding.remove(&uri);
+                self.lsp.folding_due.retain(|(_, due), _| *due != uri);
+            }
+        }
+    }
+
+    // The regions the server says can be folded in the current document.
+    pub(super) fn lsp_folding_ranges(&self) -> Vec<(usize, usize)> {
+        self.lsp_document()
+            .and_then(|(_, uri)| self.lsp.folding.get(&uri))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub(super) fn lsp_did_save(&mut self) {
+        if let Some((language, uri)) = self.lsp_document() {
+            if let Some(server) = self.lsp.servers.get_mut(&language) {
+                let _ = server.did_save(&uri);
+            }
+        }
+    }
+
+    // Handles what the servers have sent, returning whether the screen needs
+    // redrawing.
+    pub(super) fn poll_language_servers(&mut self) -> bool {
+        let mut received = Vec::new();
+        let mut stopped = Vec::new();
+        let mut started = false;
+        for (language, server) in &mut self.lsp.servers {
+            let ready = server.is_ready();
+            let (messages, running) = server.poll();
+            started |= !ready && server.is_ready();
+            received.extend(
+                messages
+                    .into_iter()
+                    .map(|message| (language.clone(), message)),
+            );
+            if !running {
+                stopped.push(language.clone());
+            }
+        }
+        for language in stopped {
+            self.lsp.servers.remove(&language);
+            self.lsp.failed.insert(language.clone());
+            self.set_status_message(&format!("The {language} language server stopped"));
+        }
+        if started {
+            self.lsp_sync();
+        }
+        self.lsp_request_folding();
+
+        let redraw = started || !received.is_empty();
+        for (language, message) in received {
+            match message {
+                Message::Notification { method, params } => {
+                    if method == "textDocument/publishDiagnostics" {
+                        if let Some((uri, diagnostics)) = lsp::parse_diagnostics(&params) {
+                            self.lsp.diagnostics.insert(uri, diagnostics);
+                            self.lsp.shown = None;
+                            self.show_diagnostic_at_cursor();
+                        }
+                    }
+                }
+                Message::Response { id, result } => {
+                    let Some(pending) = self.lsp.pending.remove(&(language, id)) else {
+                        continue;
+                    };
+                    match result {
+                        Ok(result) => self.handle_lsp_response(pending, &result),
+                        Err(message) => {
+                            self.set_status_message(&message);
+                            // Still let the completion popup stop waiting.
+                            if matches!(pending, Pending::Completion) {
+                                self.handle_lsp_response(pending, &Value::Null);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        redraw
+    }
+
+    // Asks for the folding ranges of the documents that have not changed for
+    // a moment, rather than after every key typed.
+    fn lsp_request_folding(&mut self) {
+        let due: Vec<(String, String)> = self
+            .lsp
+            .folding_due
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= FOLDING_DELAY)
+            .map(|(document, _)| document.clone())
+            .collect();
+        for (language, uri) in due {
+            self.lsp.folding_due.remove(&(language.clone(), uri.clone()));
+            let Some(server) = self.lsp.servers.get_mut(&language) else {
+                continue;
+            };
+            let params = serde_json::json!({ "textDocument": { "uri": uri } });
+            match server.request("textDocument/foldingRange", &params) {
+                Ok(id) => {
+                    self.lsp
+                        .pending
+                        .insert((language, id), Pending::FoldingRange(uri));
+                }
+                Err(err) => self
+                    .logger
+                    .error(&format!("Error talking to the {language} server: {err}")),
+            }
+        }
+    }
+
+    fn handle_lsp_response(&mut self, pending: Pending, result: &Value) {
+        match pending {
+            Pending::Hover => {
+                self.info = lsp::hover_lines(result);
+                if self.info.is_empty() {
+                    self.set_status_message("No hover information");
+                }
+            }
+            Pending::Definition => {
+                let locations = lsp::parse_locations(result);
+                match locations.first() {
+                    Some(location) => self.open_location(&location.path, location.position),
+                    None => self.set_status_message("No definition found"),
+                }
+            }
+            Pending::References => {
+                let locations = lsp::parse_locations(result);
+                if locations.is_empty() {
+                    self.set_status_message("No references found");
+                    return;
+                }
+                let results = locations
+                    .iter()
+                    .map(|location| {
+                        let line = location_line(&location.path, location.position.line);
+                        let column = lsp::grapheme_column(&line, location.position.character);
+                        let position = Position {
+                            x: column,
+                            y: location.position.line,
+                        };
+                        (location.path.clone(), position, line)
+                    })
+                    .collect();
+                self.show_results(&format!("{} references", locations.len()), results);
+            }
+            Pending::Completion => self.add_lsp_completions(lsp::completion_items(result)),
+            Pending::FoldingRange(uri) => {
+                self.lsp.folding.insert(uri, lsp::folding_ranges(result));
+                // The ranges are news even when the text is not.
+                self.folds_version = None;
+                self.update_folds(None);
+            }
+        }
+    }
+
+    fn open_location(&mut self, path: &Path, position: LspPosition) {
+        let name = super::sidebar::display_path(path);
+        self.jump_to_file(&name, |document| {
+            let y = position.line.min(document.len().saturating_sub(1));
+            let line = document.row(y).map_or("", Row::as_str);
+            let x = lsp::grapheme_column(line, position.character);
+            Position { x, y }
+        });
+    }
+
+    // Sends a request about the cursor's position, returning whether it was
+    // sent.
+    fn lsp_request(&mut self, method: &str, pending: Pending) -> bool {
+        let Some((language, _)) = self.lsp_document() else {
+            self.set_status_message("No language server for this buffer");
+            return false;
+        };
+        let Some(filename) = self.document.filename.clone() else {
+            return false;
+        };
+        let line = self
+            .document
+            .row(self.cursor_position.y)
+            .map_or("", Row::as_str);
+        let position = LspPosition {
+            line: self.cursor_position.y,
+            character: lsp::utf16_column(line, self.cursor_position.x),
+        };
+        let mut params = lsp::text_document_position(Path::new(&filename), position);
+        if matches!(pending, Pending::References) {
+            params["context"] = serde_json::json!({ "includeDeclaration": true });
+        }
+        let Some(server) = self.lsp.servers.get_mut(&language) else {
+            return false;
+        };
+        match server.request(method, &params) {
+            Ok(id) => {
+                self.lsp.pending.insert((language, id), pending);
+                true
+            }
+            Err(err) => {
+                self.set_status_message(&format!("Error talking to the server: {err}"));
+                false
+            }
+        }
+    }
+
+    pub(super) fn hover(&mut self) {
+        self.lsp_request("textDocument/hover", Pending::Hover);
+    }
+
+    pub(super) fn goto_definition(&mut self) {
+        self.lsp_request("textDocument/definition", Pending::Definition);
+    }
+
+    pub(super) fn find_references(&mut self) {
+        self.lsp_request("textDocument/references", Pending::References);
+    }
+
+    pub(super) fn lsp_completion(&mut self) -> bool {
+        self.lsp_request("textDocument/completion", Pending::Completion)
+    }
+
+    pub(super) fn current_diagnostics(&self) -> &[Diagnostic] {
+        self.document
+            .filename
+            .as_ref()
+            .map(|filename| lsp::path_to_uri(Path::new(filename)))
+            .and_then(|uri| self.lsp.diagnostics.get(&uri))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    // Puts the message of the diagnostic on the cursor's line in the message
+    // bar, once each time the cursor arrives at it.
+    pub(super) fn show_diagnostic_at_cursor(&mut self) {
+        let y = self.cursor_position.y;
+        let message = self
+            .current_diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.start.line <= y && y <= diagnostic.end.line)
+            .min_by_key(|diagnostic| diagnostic.severity)
+            .map(|diagnostic| {
+                let first_line = diagnostic.message.lines().next().unwrap_or_default();
+                format!("{}: {first_line}", diagnostic.severity.label())
+            });
+        if message != self.lsp.shown {
+            if let Some(message) = &message {
+                self.set_status_message(message);
+            }
+            self.lsp.shown = message;
+        }
+    }
+
+    // The columns of row `y` to underline for diagnostics, end exclusive.
+    pub(super) fn diagnostic_columns(&self, y: usize, row: &Row) -> Vec<(usize, usize)> {
+        self.current_diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.start.line <= y && y <= diagnostic.end.line)
+            .map(|diagnostic| {
+                let from = if diagnostic.start.line == y {
+                    lsp::grapheme_column(row.as_str(), diagnostic.start.character)
+                } else {
+                    0
+                };
+                let to = if diagnostic.end.line == y {
+                    lsp::grapheme_column(row.as_str(), diagnostic.end.character)
+                } else {
+                    row.len()
+                };
+                // Still mark empty ranges, such as a missing semicolon.
+                (from, to.max(from + 1))
+            })
+            .collect()
+    }
+}
+
+// Line `line` of the file at `path`, as saved.
+fn location_line(path: &Path, line: usize) -> String {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| text.lines().nth(line).map(String::from))
+        .unwrap_or_default()
+}
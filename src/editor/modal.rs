@@ -0,0 +1,864 @@
+use super::prompt::PromptKind;
+use super::{Editor, Selection, SelectionMode};
+use crate::keymap;
+use crate::motion::{self, TextRange};
+use crate::vim::{
+    self, Action, InsertPoint, Mode, Motion, NormalCommand, Operator, Register, Target, TextObject,
+    Vim,
+};
+use crate::Position;
+
+use termion::event::Key;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+impl Editor<'_> {
+    // Gives the modal layer the first look at every key. Returns true when
+    // the key was consumed; anything else falls through to the keymap.
+    pub(super) fn vim_keypress(&mut self, key: Key) -> bool {
+        let Some(vim) = self.vim.as_mut() else {
+            return false;
+        };
+        if vim.mode == Mode::Insert {
+            if key == Key::Esc {
+                self.vim_exit_insert();
+                return true;
+            }
+            if matches!(key, Key::Char(_) | Key::Backspace | Key::Delete) {
+                vim.record(key);
+                vim.inserted.push(key);
+            }
+            return false;
+        }
+        // Editing keys go through the vim commands so that they respect
+        // read-only buffers, `.` and registers.
+        let key = match key {
+            Key::Backspace | Key::Left => Key::Char('h'),
+            Key::Delete => Key::Char('x'),
+            Key::Down => Key::Char('j'),
+            Key::Up => Key::Char('k'),
+            Key::Right => Key::Char('l'),
+            Key::Home => Key::Char('0'),
+            Key::End => Key::Char('$'),
+            key => key,
+        };
+        if !vim.has_pending() && !matches!(key, Key::Char(_) | Key::Esc) {
+            return !keymap::starts_vim_chord(key);
+        }
+        if let Some((command, keys)) = vim.feed(key) {
+            self.vim_execute(command, keys);
+        }
+
+        true
+    }
+
+    fn vim_state(&mut self) -> &mut Vim {
+        self.vim.as_mut().expect("Modal editing is not enabled")
+    }
+
+    fn vim_execute(&mut self, command: NormalCommand, keys: Vec<Key>) {
+        let count = command.count();
+        if command.is_change() {
+            if self.document.is_read_only() {
+                self.set_status_message(super::READ_ONLY_MESSAGE);
+                return;
+            }
+            self.vim_state().start_change(keys);
+        }
+
+        match command.action {
+            Action::Move(motion) => {
+                if let Some((position, _)) =
+                    self.vim_motion(&motion, count, command.count.is_some(), false)
+                {
+                    if matches!(motion, Motion::FileStart | Motion::FileEnd) {
+                        self.jump_list.push(self.here());
+                    }
+                    self.cursor_position = position;
+                }
+            }
+            Action::Operate(operator, target) => {
+                let range = match self.vim_change_word_range(operator, &target, count) {
+                    Some(range) => Some(range),
+                    None => self.vim_target_range(target, count, command.count.is_some()),
+                };
+                if let Some(range) = range {
+                    self.vim_operate(operator, &range, command.register);
+                }
+            }
+            Action::Insert(point) => self.vim_enter_insert(point, count),
+            Action::Put { before } => self.vim_put(command.register, before, count),
+            Action::Replace(replacement) => self.vim_replace(&replacement, count),
+            Action::Repeat => {
+                for key in self.vim_state().repeat_keys(command.count) {
+                    self.handle_key(key);
+                }
+            }
+            Action::Visual { linewise } => self.vim_visual(linewise),
+            Action::CommandLine => self.vim_command_line(),
+            Action::OperateSelection(operator) => {
+                if let Some(range) = self.vim_selection_range() {
+                    self.vim_set_mode(Mode::Normal);
+                    self.vim_operate(operator, &range, command.register);
+                }
+            }
+            Action::SwapAnchor => {
+                if let Some(selection) = self.selection.as_mut() {
+                    std::mem::swap(&mut selection.anchor, &mut self.cursor_position);
+                }
+            }
+            Action::ExitVisual => self.vim_set_mode(Mode::Normal),
+            Action::JumpForward => self.jump_forward(),
+            Action::Undo => {
+                for _ in 0..count {
+                    self.undo();
+                }
+            }
+            Action::Hover => self.hover(),
+            Action::GotoDefinition => self.goto_definition(),
+            Action::FindReferences => self.find_references(),
+            Action::NextHunk => {
+                for _ in 0..count {
+                    self.next_hunk();
+                }
+            }
+            Action::PreviousHunk => {
+                for _ in 0..count {
+                    self.previous_hunk();
+                }
+            }
+            Action::DiffObtain => self.diff_obtain(),
+            Action::DiffPut => self.diff_put(),
+            Action::NextConflict => {
+                for _ in 0..count {
+                    self.next_conflict();
+                }
+            }
+            Action::PreviousConflict => {
+                for _ in 0..count {
+                    self.previous_conflict();
+                }
+            }
+            Action::ToggleFold => self.toggle_fold(),
+            Action::CloseFold => self.close_fold(),
+            Action::OpenFold => self.open_fold(),
+            Action::CloseAllFolds => self.close_all_folds(),
+            Action::OpenAllFolds => self.open_all_folds(),
+        }
+
+        if self.vim_state().mode != Mode::Insert {
+            self.vim_state().finish_change();
+            self.vim_clamp_cursor();
+        }
+    }
+
+    pub(super) fn vim_set_mode(&mut self, mode: Mode) {
+        self.vim_state().mode = mode;
+        self.selection = match mode {
+            Mode::Visual | Mode::VisualLine => Some(Selection {
+                anchor: self.selection.map_or(self.cursor_position, |s| s.anchor),
+                mode: if mode == Mode::VisualLine {
+                    SelectionMode::Line
+                } else {
+                    SelectionMode::Inclusive
+                },
+            }),
+            Mode::Normal | Mode::Insert => None,
+        };
+    }
+
+    fn vim_visual(&mut self, linewise: bool) {
+        let mode = if linewise {
+            Mode::VisualLine
+        } else {
+            Mode::Visual
+        };
+        if self.vim_state().mode == mode {
+            self.vim_set_mode(Mode::Normal);
+        } else {
+            self.vim_set_mode(mode);
+        }
+    }
+
+    // Normal mode keeps the cursor on a character rather than past the end of
+    // the line, and on a row rather than past the end of the document.
+    fn vim_clamp_cursor(&mut self) {
+        let last_row = self.document.len().saturating_sub(1);
+        let y = self.cursor_position.y.min(last_row);
+        let x = self
+            .cursor_position
+            .x
+            .min(self.row_len(y).saturating_sub(1));
+        self.cursor_position = Position { x, y };
+    }
+
+    fn row_len(&self, y: usize) -> usize {
+        self.document.row(y).map_or(0, crate::Row::len)
+    }
+
+    fn vim_motion(
+        &mut self,
+        motion: &Motion,
+        count: usize,
+        explicit_count: bool,
+        operator_pending: bool,
+    ) -> Option<(Position, MotionKind)> {
+        let at = self.cursor_position;
+        let last_row = self.document.len().saturating_sub(1);
+        let document = &self.document;
+        let line = |y: usize| Position {
+            x: motion::first_non_blank(document, y),
+            y,
+        };
+
+        let target = match motion {
+            Motion::Left => (
+                Position {
+                    x: at.x.saturating_sub(count),
+                    y: at.y,
+                },
+                MotionKind::Exclusive,
+            ),
+            Motion::Right => {
+                let row_len = self.row_len(at.y);
+                let limit = if operator_pending {
+                    row_len
+                } else {
+                    row_len.saturating_sub(1)
+                };
+                (
+                    Position {
+                        x: at.x.saturating_add(count).min(limit),
+                        y: at.y,
+                    },
+                    MotionKind::Exclusive,
+                )
+            }
+            Motion::Up => (
+                Position {
+                    x: at.x,
+                    y: self.rows_above(at.y, count),
+                },
+                MotionKind::Linewise,
+            ),
+            Motion::Down => (
+                Position {
+                    x: at.x,
+                    y: self.rows_below(at.y, count),
+                },
+                MotionKind::Linewise,
+            ),
+            Motion::NextLineStart => (
+                line(at.y.saturating_add(count).min(last_row)),
+                MotionKind::Linewise,
+            ),
+            Motion::WordForward { .. } | Motion::WordBackward { .. } | Motion::WordEnd { .. } => {
+                self.vim_word_motion(motion, count, operator_pending)
+            }
+            Motion::LineStart => (Position { x: 0, y: at.y }, MotionKind::Exclusive),
+            Motion::FirstNonBlank => (line(at.y), MotionKind::Exclusive),
+            Motion::LineEnd => {
+                let y = at.y.saturating_add(count - 1).min(last_row);
+                (
+                    Position {
+                        x: self.row_len(y).saturating_sub(1),
+                        y,
+                    },
+                    MotionKind::Inclusive,
+                )
+            }
+            Motion::FileStart | Motion::FileEnd => {
+                let y = if explicit_count {
+                    count.saturating_sub(1).min(last_row)
+                } else if *motion == Motion::FileStart {
+                    0
+                } else {
+                    last_row
+                };
+                (line(y), MotionKind::Linewise)
+            }
+            Motion::Find {
+                target,
+                forward,
+                till,
+            } => {
+                self.vim_state().last_find = Some((target.clone(), *forward, *till));
+                return self.vim_find(target, *forward, *till, false, count);
+            }
+            Motion::RepeatFind { reverse } => {
+                let (target, forward, till) = self.vim_state().last_find.clone()?;
+                return self.vim_find(&target, forward != *reverse, till, true, count);
+            }
+            Motion::ParagraphForward | Motion::ParagraphBackward => {
+                let mut y = at.y;
+                for _ in 0..count {
+                    y = if *motion == Motion::ParagraphForward {
+                        motion::paragraph_forward(document, y + 1)
+                    } else {
+                        motion::paragraph_backward(document, y.saturating_sub(1))
+                    };
+                }
+                (Position { x: 0, y }, MotionKind::Exclusive)
+            }
+        };
+
+        Some(target)
+    }
+
+    fn vim_word_motion(
+        &self,
+        motion: &Motion,
+        count: usize,
+        operator_pending: bool,
+    ) -> (Position, MotionKind) {
+        let at = self.cursor_position;
+        let document = &self.document;
+        let mut position = at;
+        for _ in 0..count {
+            position = match *motion {
+                Motion::WordForward { big } => motion::word_forward(document, position, big),
+                Motion::WordBackward { big } => motion::word_backward(document, position, big),
+                Motion::WordEnd { big } => motion::word_end(document, position, big),
+                _ => position,
+            };
+        }
+
+        match motion {
+            Motion::WordEnd { .. } => (position, MotionKind::Inclusive),
+            // Like vim, an operator never reaches past the last word on a line
+            // into the indentation of the next one.
+            Motion::WordForward { .. }
+                if operator_pending
+                    && position.y > at.y
+                    && position.x <= motion::first_non_blank(document, position.y) =>
+            {
+                let y = position.y - 1;
+                (
+                    Position {
+                        x: self.row_len(y),
+                        y,
+                    },
+                    MotionKind::Exclusive,
+                )
+            }
+            _ => (position, MotionKind::Exclusive),
+        }
+    }
+
+    fn vim_find(
+        &self,
+        target: &str,
+        forward: bool,
+        till: bool,
+        repeat: bool,
+        count: usize,
+    ) -> Option<(Position, MotionKind)> {
+        let position = motion::find_in_line(
+            &self.document,
+            self.cursor_position,
+            target,
+            forward,
+            till,
+            repeat,
+            count,
+        )?;
+        let kind = if forward {
+            MotionKind::Inclusive
+        } else {
+            MotionKind::Exclusive
+        };
+
+        Some((position, kind))
+    }
+
+    // `cw` on a word changes only to the end of that word, like `ce`, rather
+    // than up to the start of the next one.
+    fn vim_change_word_range(
+        &self,
+        operator: Operator,
+        target: &Target,
+        count: usize,
+    ) -> Option<TextRange> {
+        let Target::Motion(Motion::WordForward { big }) = target else {
+            return None;
+        };
+        let at = self.cursor_position;
+        let on_word = self
+            .document
+            .row(at.y)
+            .and_then(|row| row.grapheme(at.x))
+            .is_some_and(|grapheme| !grapheme.trim().is_empty());
+        if operator != Operator::Change || !on_word {
+            return None;
+        }
+
+        let word = motion::word_object(&self.document, at, true, *big)?;
+        let mut last = Position {
+            x: word.end.x - 1,
+            y: word.end.y,
+        };
+        for _ in 1..count {
+            last = motion::word_end(&self.document, last, *big);
+        }
+
+        Some(TextRange {
+            start: at,
+            end: Position {
+                x: (last.x + 1).min(self.row_len(last.y)),
+                y: last.y,
+            },
+            linewise: false,
+        })
+    }
+
+    fn vim_target_range(
+        &mut self,
+        target: Target,
+        count: usize,
+        explicit_count: bool,
+    ) -> Option<TextRange> {
+        let at = self.cursor_position;
+        let document = &self.document;
+        let range = match target {
+            Target::Line => TextRange {
+                start: Position { x: 0, y: at.y },
+                end: Position {
+                    x: 0,
+                    y: at
+                        .y
+                        .saturating_add(count - 1)
+                        .min(document.len().saturating_sub(1)),
+                },
+                linewise: true,
+            },
+            Target::Object { object, inner } => match object {
+                TextObject::Word { big } => motion::word_object(document, at, inner, big)?,
+                TextObject::Quote(quote) => motion::quote_object(document, at, &quote, inner)?,
+                TextObject::Bracket(open, close) => {
+                    motion::bracket_object(document, at, open, close, inner)?
+                }
+                TextObject::Paragraph => motion::paragraph_object(document, at.y, inner)?,
+            },
+            Target::Motion(motion) => {
+                let (to, kind) = self.vim_motion(&motion, count, explicit_count, true)?;
+                let (start, mut end) = if at <= to { (at, to) } else { (to, at) };
+                match kind {
+                    MotionKind::Linewise => TextRange {
+                        start,
+                        end,
+                        linewise: true,
+                    },
+                    MotionKind::Exclusive => TextRange {
+                        start,
+                        end,
+                        linewise: false,
+                    },
+                    MotionKind::Inclusive => {
+                        end.x = (end.x + 1).min(self.row_len(end.y));
+                        TextRange {
+                            start,
+                            end,
+                            linewise: false,
+                        }
+                    }
+                }
+            }
+        };
+
+        Some(range)
+    }
+
+    fn vim_selection_range(&self) -> Option<TextRange> {
+        let selection = self.selection?;
+        let (start, end) = if selection.anchor <= self.cursor_position {
+            (selection.anchor, self.cursor_position)
+        } else {
+            (self.cursor_position, selection.anchor)
+        };
+        if selection.mode == SelectionMode::Line {
+            return Some(TextRange {
+                start,
+                end,
+                linewise: true,
+            });
+        }
+        let end = if end.x < self.row_len(end.y) {
+            Position {
+                x: end.x + 1,
+                y: end.y,
+            }
+        } else if end.y + 1 < self.document.len() {
+            Position { x: 0, y: end.y + 1 }
+        } else {
+            end
+        };
+
+        Some(TextRange {
+            start,
+            end,
+            linewise: false,
+        })
+    }
+
+    fn vim_operate(&mut self, operator: Operator, range: &TextRange, register: Option<char>) {
+        if range.linewise {
+            self.vim_operate_lines(operator, range.start.y, range.end.y, register);
+            return;
+        }
+        let text = self.document.text_range(&range.start, &range.end);
+        self.vim_state().store(
+            register,
+            Register {
+                text,
+                linewise: false,
+            },
+            operator == Operator::Yank,
+        );
+        if operator != Operator::Yank {
+            self.document.delete_range(&range.start, &range.end);
+        }
+        self.cursor_position = range.start;
+        if operator == Operator::Change {
+            self.vim_enter_insert(InsertPoint::Cursor, 1);
+        }
+    }
+
+    fn vim_operate_lines(
+        &mut self,
+        operator: Operator,
+        first: usize,
+        last: usize,
+        register: Option<char>,
+    ) {
+        if self.document.is_empty() {
+            return;
+        }
+        let last = last.min(self.document.len() - 1);
+        let last_end = Position {
+            x: self.row_len(last),
+            y: last,
+        };
+        let text = self
+            .document
+            .text_range(&Position { x: 0, y: first }, &last_end);
+        self.vim_state().store(
+            register,
+            Register {
+                text,
+                linewise: true,
+            },
+            operator == Operator::Yank,
+        );
+
+        let start = Position { x: 0, y: first };
+        match operator {
+            Operator::Yank => self.cursor_position = start,
+            Operator::Change => {
+                self.document.delete_range(&start, &last_end);
+                self.cursor_position = start;
+                self.vim_enter_insert(InsertPoint::Cursor, 1);
+            }
+            Operator::Delete => {
+                let end_of_document = self.document.end_position();
+                if last + 1 < self.document.len() {
+                    self.document
+                        .delete_range(&start, &Position { x: 0, y: last + 1 });
+                } else if first > 0 {
+                    let previous_end = Position {
+                        x: self.row_len(first - 1),
+                        y: first - 1,
+                    };
+                    self.document.delete_range(&previous_end, &end_of_document);
+                } else {
+                    self.document.delete_range(&start, &end_of_document);
+                }
+                let y = first.min(self.document.len().saturating_sub(1));
+                self.cursor_position = Position {
+                    x: motion::first_non_blank(&self.document, y),
+                    y,
+                };
+            }
+        }
+    }
+
+    fn vim_enter_insert(&mut self, point: InsertPoint, count: usize) {
+        let Position { x, y } = self.cursor_position;
+        match point {
+            InsertPoint::Cursor => (),
+            InsertPoint::After => self.cursor_position.x = (x + 1).min(self.row_len(y)),
+            InsertPoint::LineStart => {
+                self.cursor_position.x = motion::first_non_blank(&self.document, y);
+            }
+            InsertPoint::LineEnd => self.cursor_position.x = self.row_len(y),
+            InsertPoint::LineBelow => {
+                let end_of_line = Position {
+                    x: self.row_len(y),
+                    y,
+                };
+                self.cursor_position = self.document.insert_str(&end_of_line, "\n");
+            }
+            InsertPoint::LineAbove => {
+                self.document.insert_str(&Position { x: 0, y }, "\n");
+                self.cursor_position = Position { x: 0, y };
+            }
+        }
+
+        let vim = self.vim_state();
+        vim.mode = Mode::Insert;
+        vim.insert_count = count;
+        vim.insert_point = point;
+        vim.inserted.clear();
+    }
+
+    fn vim_exit_insert(&mut self) {
+        let vim = self.vim_state();
+        let keys = std::mem::take(&mut vim.inserted);
+        let repeats = vim.insert_count.saturating_sub(1);
+        let new_lines = matches!(
+            vim.insert_point,
+            InsertPoint::LineBelow | InsertPoint::LineAbove
+        );
+        vim.record(Key::Esc);
+        vim.finish_change();
+        vim.mode = Mode::Normal;
+
+        for _ in 0..repeats {
+            if new_lines {
+                self.handle_mapped_key(Key::Char('\n'));
+            }
+            for key in &keys {
+                self.handle_mapped_key(*key);
+            }
+        }
+        self.cursor_position.x = self.cursor_position.x.saturating_sub(1);
+        self.vim_clamp_cursor();
+    }
+
+    fn vim_put(&mut self, register: Option<char>, before: bool, count: usize) {
+        let Some(Register { text, linewise }) = self.vim_state().register(register).cloned() else {
+            self.set_status_message("Nothing in register");
+            return;
+        };
+        let Position { x, y } = self.cursor_position;
+
+        if linewise {
+            let lines = vec![text; count].join("\n");
+            let target_y = if before || self.document.is_empty() {
+                y
+            } else {
+                y + 1
+            };
+            if target_y < self.document.len() {
+                self.document
+                    .insert_str(&Position { x: 0, y: target_y }, &format!("{lines}\n"));
+            } else if self.document.is_empty() {
+                self.document.insert_str(&Position::default(), &lines);
+            } else {
+                let end = self.document.end_position();
+                self.document.insert_str(&end, &format!("\n{lines}"));
+            }
+            self.cursor_position = Position {
+                x: motion::first_non_blank(&self.document, target_y),
+                y: target_y,
+            };
+            return;
+        }
+
+        let text = text.repeat(count);
+        let at = if before || self.row_len(y) == 0 {
+            Position { x, y }
+        } else {
+            Position { x: x + 1, y }
+        };
+        let end = self.document.insert_str(&at, &text);
+        self.cursor_position = Position {
+            x: end.x.saturating_sub(1),
+            y: end.y,
+        };
+    }
+
+    fn vim_replace(&mut self, replacement: &str, count: usize) {
+        let Position { x, y } = self.cursor_position;
+        if x + count > self.row_len(y) {
+            return;
+        }
+        let end = Position { x: x + count, y };
+        self.document.delete_range(&self.cursor_position, &end);
+        if replacement == "\n" {
+            self.cursor_position = self.document.insert_str(&Position { x, y }, "\n");
+            return;
+        }
+        self.document
+            .insert_str(&Position { x, y }, &replacement.repeat(count));
+        self.cursor_position.x = x + count - 1;
+    }
+
+    fn vim_command_line(&mut self) {
+        let selection = self.selection.map(|selection| {
+            let (a, b) = (selection.anchor.y, self.cursor_position.y);
+            (a.min(b), a.max(b))
+        });
+        self.vim_set_mode(Mode::Normal);
+
+        let Ok(Some(input)) = self.prompt(PromptKind::Ex, ":") else {
+            return;
+        };
+        let last_row = self.document.len().saturating_sub(1);
+        let (range, command) =
+            vim::parse_ex_range(input.trim(), self.cursor_position.y, last_row, selection);
+        let command = command.trim();
+
+        match command {
+            "" => {
+                if let Some((_, line)) = range {
+                    self.jump_to(Position {
+                        x: motion::first_non_blank(&self.document, line),
+                        y: line,
+                    });
+                }
+            }
+            "w" => self.handle_save(),
+            "bn" | "bnext" => self.next_buffer(),
+            "bp" | "bprevious" => self.previous_buffer(),
+            "Ex" | "Explore" => self.focus_explorer(),
+            "find" | "fin" => self.find_file(),
+            "grep" | "gr" => self.grep_prompt(),
+            "make" | "mak" => self.run_build(),
+            "cn" | "cnext" => self.next_error(),
+            "cp" | "cN" | "cprevious" => self.previous_error(),
+            "copen" | "cope" | "cw" | "cwindow" => self.show_build_list(),
+            "diffget" | "diffg" => self.diff_obtain(),
+            "diffput" | "diffpu" => self.diff_put(),
+            "diffoff" | "diffo" => self.diff_off(),
+            "DiffOrig" => self.diff_with_saved(),
+            "set ro" | "set readonly" => self.document.set_read_only(true),
+            "set noro" | "set noreadonly" => self.document.set_read_only(false),
+            "q" => {
+                if self.unsaved_buffers() > 0 {
+                    self.set_status_message("No write since last change (add ! to override)");
+                } else {
+                    self.should_quit = true;
+                }
+            }
+            "q!" => self.should_quit = true,
+            "wq" | "x" => {
+                self.handle_save();
+                if !self.document.is_dirty() {
+                    self.should_quit = true;
+                }
+            }
+            _ => {
+                let lines = range.map_or(0..self.document.len(), |(first, last)| first..last + 1);
+                if let Some(shell) = command.strip_prefix('!') {
+                    if range.is_some() {
+                        self.filter_lines(shell.trim(), lines);
+                    } else {
+                        self.pipe_lines_to_buffer(shell.trim(), 0..0);
+                    }
+                } else if let Some(shell) = ["r !", "r!", "read !", "read!"]
+                    .iter()
+                    .find_map(|prefix| command.strip_prefix(prefix))
+                {
+                    let below = range.map_or(self.cursor_position.y, |(_, last)| last);
+                    self.read_command_output(shell.trim(), below);
+                } else if let Some(shell) = command.strip_prefix("w !") {
+                    self.pipe_lines_to_buffer(shell.trim(), lines);
+                } else if let Some(path) = command.strip_prefix("e ") {
+                    if let Err(err) = self.open_file(path.trim()) {
+                        self.set_status_message(&format!("Could not open {}: {err}", path.trim()));
+                    }
+                } else if let Some(path) = command.strip_prefix("w ") {
+                    // Like vim, this only names the buffer when it has no file.
+                    if self.document.filename.is_some() {
+                        self.write_copy(path.trim());
+                    } else {
+                        self.save_as(path.trim());
+                    }
+                } else if let Some(path) = command
+                    .strip_prefix("saveas ")
+                    .or_else(|| command.strip_prefix("sav "))
+                {
+                    self.save_as(path.trim());
+                } else if let Some(pattern) = command
+                    .strip_prefix("grep ")
+                    .or_else(|| command.strip_prefix("gr "))
+                {
+                    self.grep(pattern.trim());
+                } else if let Some(args) = substitute_args(command) {
+                    let (first, last) =
+                        range.unwrap_or((self.cursor_position.y, self.cursor_position.y));
+                    self.vim_substitute(args, first, last);
+                } else {
+                    self.set_status_message(&format!("Not an editor command: {command}"));
+                }
+            }
+        }
+    }
+
+    fn vim_substitute(&mut self, args: &str, first: usize, last: usize) {
+        let substitution = match vim::parse_substitute(args) {
+            Ok(substitution) => substitution,
+            Err(message) => {
+                self.set_status_message(&message);
+                return;
+            }
+        };
+
+        let mut replaced = 0;
+        let mut lines = 0;
+        let mut last_changed = None;
+        for y in first..=last.min(self.document.len().saturating_sub(1)) {
+            let Some(row) = self.document.row(y) else {
+                break;
+            };
+            let text = row.as_str();
+            let matches = substitution.pattern.find_iter(text).count();
+            if matches == 0 {
+                continue;
+            }
+            let new_text = if substitution.global {
+                replaced += matches;
+                substitution
+                    .pattern
+                    .replace_all(text, substitution.replacement.as_str())
+            } else {
+                replaced += 1;
+                substitution
+                    .pattern
+                    .replace(text, substitution.replacement.as_str())
+            }
+            .into_owned();
+            self.document.replace_row(y, &new_text);
+            lines += 1;
+            last_changed = Some(y);
+        }
+
+        match last_changed {
+            Some(y) => {
+                self.cursor_position = Position {
+                    x: motion::first_non_blank(&self.document, y),
+                    y,
+                };
+                self.set_status_message(&format!("{replaced} substitutions on {lines} lines"));
+            }
+            None => self.set_status_message(&format!(
+                "Pattern not found: {}",
+                substitution.pattern.as_str()
+            )),
+        }
+    }
+}
+
+// The `/pattern/replacement/flags` part of `:s` or `:substitute`. Like vim,
+// any character but a letter or digit can delimit the pattern.
+fn substitute_args(command: &str) -> Option<&str> {
+    ["substitute", "s"]
+        .iter()
+        .find_map(|name| command.strip_prefix(name))
+        .filter(|args| args.chars().next().is_some_and(|c| !c.is_alphanumeric()))
+}
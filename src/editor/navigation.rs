@@ -0,0 +1,98 @@
+use super::prompt::PromptKind;
+use super::Editor;
+use crate::goto::{self, GotoTarget};
+use crate::jumplist::Jump;
+use crate::{motion, Document, Position};
+
+impl Editor<'_> {
+    pub(super) fn goto_prompt(&mut self) {
+        let Ok(Some(input)) = self.prompt(PromptKind::Goto, "Go to line[:col], +N, -N or N%:") else {
+            return;
+        };
+        match goto::parse_goto(&input, self.cursor_position.y, self.document.len()) {
+            Ok(target) => self.goto(&target),
+            Err(message) => self.set_status_message(&message),
+        }
+    }
+
+    pub(super) fn goto(&mut self, target: &GotoTarget) {
+        let position = |document: &Document| {
+            let y = target.line.min(document.len().saturating_sub(1));
+            let x = match target.column {
+                Some(column) => column.min(document.row(y).map_or(0, crate::Row::len)),
+                None => motion::first_non_blank(document, y),
+            };
+            Position { x, y }
+        };
+        match &target.path {
+            Some(path) => {
+                self.jump_to_file(path, position);
+            }
+            None => self.jump_to(position(&self.document)),
+        }
+    }
+
+    // Moves the cursor somewhere far away, remembering where it came from and
+    // centring the new position on screen.
+    pub(super) fn jump_to(&mut self, position: Position) {
+        self.jump_list.push(self.here());
+        self.cursor_position = position;
+        self.center_on_cursor();
+    }
+
+    // Opens `path` and jumps to where `position` picks in it. Returns whether
+    // the file could be opened.
+    pub(super) fn jump_to_file(
+        &mut self,
+        path: &str,
+        position: impl FnOnce(&Document) -> Position,
+    ) -> bool {
+        let buffer = self.current_buffer;
+        if let Err(err) = self.open_file(path) {
+            self.set_status_message(&format!("Could not open {path}: {err}"));
+            return false;
+        }
+        let position = position(&self.document);
+        if self.current_buffer == buffer {
+            self.jump_to(position);
+        } else {
+            // Switching buffers already remembered where the jump started.
+            self.cursor_position = position;
+            self.center_on_cursor();
+        }
+        true
+    }
+
+    pub(super) fn center_on_cursor(&mut self) {
+        self.offset.y = self
+            .cursor_position
+            .y
+            .saturating_sub(self.text_height() / 2);
+    }
+
+    pub(super) fn jump_back(&mut self) {
+        match self.jump_list.back(self.here()) {
+            Some(jump) => self.move_to_jump(jump),
+            None => self.set_status_message("Already at the oldest jump"),
+        }
+    }
+
+    pub(super) fn jump_forward(&mut self) {
+        match self.jump_list.forward() {
+            Some(jump) => self.move_to_jump(jump),
+            None => self.set_status_message("Already at the newest jump"),
+        }
+    }
+
+    // Jump list entries may point past text deleted since they were made.
+    fn move_to_jump(&mut self, jump: Jump) {
+        self.change_buffer(jump.buffer);
+        let position = jump.position;
+        let y = position.y.min(self.document.len().saturating_sub(1));
+        let x = position
+            .x
+            .min(self.document.row(y).map_or(0, crate::Row::len));
+        self.cursor_position = Position { x, y };
+        self.center_on_cursor();
+    }
+}
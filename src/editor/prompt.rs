@@ -0,0 +1,147 @@
+use super::Editor;
+use crate::completion;
+use crate::lineedit::LineEditor;
+
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Each kind of prompt keeps its own history, so Up in "Go to" does not offer
+// file names typed into "Open file".
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptKind {
+    Path,
+    Goto,
+    Ex,
+    Search,
+    Replacement,
+    Shell,
+    // The palette uses Up and Down to move its selection instead.
+    Command,
+}
+
+impl PromptKind {
+    fn has_history(self) -> bool {
+        self != PromptKind::Command
+    }
+}
+
+impl Editor<'_> {
+    pub(super) fn prompt(
+        &mut self,
+        kind: PromptKind,
+        prompt: &str,
+    ) -> Result<Option<String>, std::io::Error> {
+        self.read_prompt(kind, prompt, "", |_, _, _| ())
+            .map(|input| input.filter(|input| !input.is_empty()))
+    }
+
+    // Like `prompt`, but accepts an empty answer rather than treating it as
+    // cancelling.
+    pub(super) fn prompt_allow_empty(
+        &mut self,
+        kind: PromptKind,
+        prompt: &str,
+    ) -> Result<Option<String>, std::io::Error> {
+        self.read_prompt(kind, prompt, "", |_, _, _| ())
+    }
+
+    // Like `prompt`, but starts with `initial` already typed in.
+    pub(super) fn prompt_prefilled(
+        &mut self,
+        kind: PromptKind,
+        prompt: &str,
+        initial: &str,
+    ) -> Result<Option<String>, std::io::Error> {
+        self.read_prompt(kind, prompt, initial, |_, _, _| ())
+            .map(|input| input.filter(|input| !input.is_empty()))
+    }
+
+    // Reads a line of input in the message bar, calling `callback` with the
+    // input so far after every key press.
+    pub(super) fn prompt_with(
+        &mut self,
+        kind: PromptKind,
+        prompt: &str,
+        callback: fn(&mut Self, &str, Key),
+    ) -> Result<Option<String>, std::io::Error> {
+        self.read_prompt(kind, prompt, "", callback)
+            .map(|input| input.filter(|input| !input.is_empty()))
+    }
+
+    fn read_prompt(
+        &mut self,
+        kind: PromptKind,
+        prompt: &str,
+        initial: &str,
+        callback: fn(&mut Self, &str, Key),
+    ) -> Result<Option<String>, std::io::Error> {
+        let mut line = LineEditor::default();
+        line.set_text(initial);
+        // While browsing history, the index of the entry shown and the input
+        // that was there before browsing started.
+        let mut browsing: Option<(usize, String)> = None;
+        let accepted = loop {
+            self.set_status_message(&format!("{prompt} {}", line.text()));
+            self.prompt_cursor = Some(prompt.graphemes(true).count() + 1 + line.cursor());
+            self.refresh_screen()?;
+
+            let key = self.next_key()?;
+            if key != Key::Char('\t') {
+                self.completions.clear();
+            }
+            match key {
+                Key::Char('\n') => {
+                    callback(self, line.text(), key);
+                    break true;
+                }
+                Key::Esc | Key::Ctrl('g') => break false,
+                Key::Char('\t') if kind == PromptKind::Path => self.complete_prompt(&mut line),
+                Key::Up | Key::Down if kind.has_history() => {
+                    let history = self.histories.entry(kind).or_default();
+                    let index = match (key, &browsing) {
+                        (Key::Up, None) => history.len().checked_sub(1),
+                        (Key::Up, Some((index, _))) => Some(index.saturating_sub(1)),
+                        (_, Some((index, _))) => Some(index + 1),
+                        _ => None,
+                    };
+                    match index.and_then(|index| Some((index, history.get(index)?))) {
+                        Some((index, entry)) => {
+                            let draft = browsing
+                                .take()
+                                .map_or_else(|| line.text().to_string(), |(_, draft)| draft);
+                            line.set_text(entry);
+                            browsing = Some((index, draft));
+                        }
+                        None if key == Key::Down => {
+                            if let Some((_, draft)) = browsing.take() {
+                                line.set_text(&draft);
+                            }
+                        }
+                        None => (),
+                    }
+                }
+                _ => {
+                    line.handle(key);
+                }
+            }
+            callback(self, line.text(), key);
+        };
+        self.prompt_cursor = None;
+        self.completions.clear();
+        self.set_status_message("");
+        if !accepted {
+            return Ok(None);
+        }
+        if kind.has_history() {
+            self.histories.entry(kind).or_default().push(line.text());
+        }
+
+        Ok(Some(line.text().to_string()))
+    }
+
+    fn complete_prompt(&mut self, line: &mut LineEditor) {
+        let completion = completion::complete_path(line.text());
+        line.set_text(&completion.text);
+        self.completions = completion.candidates;
+    }
+}
@@ -0,0 +1,123 @@
+use super::{Editor, Selection, SelectionMode};
+use crate::{Command, Position};
+
+impl Editor<'_> {
+    pub(super) fn set_mark(&mut self) {
+        self.selection = Some(Selection {
+            anchor: self.cursor_position,
+            mode: SelectionMode::Exclusive,
+        });
+        self.set_status_message("Mark set");
+    }
+
+    // Typing over an active region drops the mark, as transient mark mode
+    // does. Vim's visual selection is left to the modal layer.
+    pub(super) fn deactivate_mark(&mut self) {
+        if matches!(self.selection, Some(selection) if selection.mode == SelectionMode::Exclusive) {
+            self.selection = None;
+        }
+    }
+
+    fn region(&self) -> Option<(Position, Position)> {
+        let anchor = self.selection?.anchor;
+        let cursor = self.cursor_position;
+
+        Some(if anchor <= cursor {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        })
+    }
+
+    fn last_command_was_kill(&self) -> bool {
+        matches!(
+            self.last_command,
+            Some(Command::KillLine | Command::KillRegion)
+        )
+    }
+
+    fn kill(&mut self, text: String, before: bool) {
+        if self.last_command_was_kill() {
+            self.kill_ring.append(&text, before);
+        } else {
+            self.kill_ring.push(text);
+        }
+    }
+
+    pub(super) fn kill_line(&mut self) {
+        let start = self.cursor_position;
+        let Some(row) = self.document.row(start.y) else {
+            return;
+        };
+        let end = if start.x < row.len() {
+            Position {
+                x: row.len(),
+                y: start.y,
+            }
+        } else if start.y + 1 < self.document.len() {
+            Position {
+                x: 0,
+                y: start.y + 1,
+            }
+        } else {
+            return;
+        };
+
+        let text = self.document.text_range(&start, &end);
+        self.document.delete_range(&start, &end);
+        self.kill(text, false);
+    }
+
+    pub(super) fn kill_region(&mut self) {
+        let Some((start, end)) = self.region() else {
+            self.set_status_message("The mark is not set now, so there is no region");
+            return;
+        };
+        let text = self.document.text_range(&start, &end);
+        self.document.delete_range(&start, &end);
+        self.kill(text, end == self.cursor_position && start != end);
+        self.cursor_position = start;
+        self.selection = None;
+    }
+
+    pub(super) fn copy_region(&mut self) {
+        let Some((start, end)) = self.region() else {
+            self.set_status_message("The mark is not set now, so there is no region");
+            return;
+        };
+        let text = self.document.text_range(&start, &end);
+        self.kill_ring.push(text);
+        self.selection = None;
+        self.set_status_message("Region copied");
+    }
+
+    pub(super) fn yank(&mut self) {
+        let Some(text) = self.kill_ring.current().map(str::to_string) else {
+            self.set_status_message("Kill ring is empty");
+            return;
+        };
+        self.deactivate_mark();
+        self.insert_yanked(&text);
+    }
+
+    pub(super) fn yank_pop(&mut self) {
+        let previous_was_yank = matches!(self.last_command, Some(Command::Yank | Command::YankPop));
+        let Some((start, end)) = self.last_yank.filter(|_| previous_was_yank) else {
+            self.set_status_message("Previous command was not a yank");
+            return;
+        };
+        let Some(text) = self.kill_ring.rotate().map(str::to_string) else {
+            return;
+        };
+        self.document.delete_range(&start, &end);
+        self.cursor_position = start;
+        self.insert_yanked(&text);
+    }
+
+    fn insert_yanked(&mut self, text: &str) {
+        let start = self.cursor_position;
+        let end = self.document.insert_str(&start, text);
+        self.cursor_position = end;
+        self.last_yank = Some((start, end));
+    }
+}
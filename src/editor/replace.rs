@@ -0,0 +1,434 @@
+use super::prompt::PromptKind;
+use super::sidebar::display_path;
+use super::{Buffer, Editor};
+use crate::grep;
+use crate::project;
+use crate::replace::{self, FileChanges};
+use crate::{Document, Position};
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use termion::event::Key;
+
+const HELP: &str =
+    "Space: include/exclude  a: apply to buffers  w: write files  Enter: open  q: cancel";
+
+// A proposed replace-in-files, shown as a diff in its own read-only buffer
+// until it is applied or cancelled.
+pub(super) struct Replace {
+    buffer: usize,
+    title: String,
+    files: Vec<FileChanges>,
+    // For each row of the preview, the file it belongs to and the hunk, if
+    // any, that it shows.
+    rows: Vec<Option<(usize, Option<usize>)>>,
+}
+
+// What the last applied replace changed, so it can be reverted: each file
+// with its text before and after.
+pub(super) struct AppliedReplace {
+    on_disk: bool,
+    files: Vec<(String, String, String)>,
+}
+
+impl Editor<'_> {
+    pub(super) fn replace_prompt(&mut self) {
+        let Ok(Some(pattern)) = self.prompt(PromptKind::Search, "Replace in files (regex):") else {
+            return;
+        };
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.set_status_message(&format!("Invalid regex: {err}"));
+                return;
+            }
+        };
+        let prompt = format!("Replace {pattern} with:");
+        let Ok(Some(replacement)) = self.prompt_allow_empty(PromptKind::Replacement, &prompt)
+        else {
+            return;
+        };
+        self.set_status_message(&format!("Searching for {pattern}..."));
+        if let Err(err) = self.refresh_screen() {
+            self.logger
+                .error(&format!("Error refreshing screen: {err}"));
+        }
+
+        let files = self.find_changes(&regex, &replacement);
+        if files.is_empty() {
+            self.set_status_message(&format!("No matches for {pattern}"));
+            return;
+        }
+        self.show_replace(format!("Replace {pattern} with {replacement:?}"), files);
+    }
+
+    // The changes the replacement would make to the project around the
+    // working directory. Files open in a buffer are read from the buffer,
+    // unsaved changes and all.
+    fn find_changes(&self, regex: &Regex, replacement: &str) -> Vec<FileChanges> {
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let root = project::project_root(&cwd);
+        // Files are told apart by their canonical path, as one can be reached
+        // by several names, and listed by the first name seen.
+        let mut paths: BTreeMap<PathBuf, String> = BTreeMap::new();
+        let found = grep::search(&root, regex.clone())
+            .into_iter()
+            .map(|found| display_path(&found.path));
+        let open = (0..self.buffers.len())
+            .filter_map(|index| self.buffer_document(index).filename.clone())
+            .filter(|filename| {
+                std::path::absolute(filename).is_ok_and(|path| path.starts_with(&root))
+            });
+        for path in found.chain(open) {
+            let canonical = fs::canonicalize(&path)
+                .or_else(|_| std::path::absolute(&path))
+                .unwrap_or_else(|_| PathBuf::from(&path));
+            paths.entry(canonical).or_insert(path);
+        }
+
+        paths
+            .into_values()
+            .filter_map(|path| {
+                let text = match self.buffer_index(&path) {
+                    Some(index) => self.buffer_document(index).contents(),
+                    None => fs::read_to_string(&path).ok()?,
+                };
+                let hunks = replace::hunks(&text, regex, replacement);
+                (!hunks.is_empty()).then_some(FileChanges { path, hunks })
+            })
+            .collect()
+    }
+
+    fn show_replace(&mut self, title: String, files: Vec<FileChanges>) {
+        // A new preview reuses the previous one's buffer.
+        let index = if let Some(replace) = &self.replace {
+            self.buffers[replace.buffer] = Buffer::default();
+            replace.buffer
+        } else {
+            self.buffers.push(Buffer::default());
+            self.buffers.len() - 1
+        };
+        if index == self.current_buffer {
+            self.show_buffer(index);
+        } else {
+            self.switch_to_buffer(index);
+        }
+        self.replace = Some(Replace {
+            buffer: index,
+            title,
+            files,
+            rows: Vec::new(),
+        });
+        self.render_replace();
+        // Start on the first hunk.
+        self.cursor_position = Position { x: 0, y: 4 };
+        self.set_status_message(HELP);
+    }
+
+    // Rewrites the preview buffer from the proposed changes.
+    fn render_replace(&mut self) {
+        let Some(replace) = self.replace.as_mut() else {
+            return;
+        };
+        let hunks: usize = replace.files.iter().map(|file| file.hunks.len()).sum();
+        let included: usize = replace
+            .files
+            .iter()
+            .map(|file| file.included().count())
+            .sum();
+        let mut lines = vec![
+            format!(
+                "{}: {included} of {hunks} hunks in {} files",
+                replace.title,
+                replace.files.len()
+            ),
+            String::from(HELP),
+        ];
+        let mut rows = vec![None, None];
+        for (file_index, file) in replace.files.iter().enumerate() {
+            lines.push(String::new());
+            rows.push(None);
+            let count = file.included().count();
+            lines.push(format!(
+                "{} ({count}/{} hunks)",
+                file.path,
+                file.hunks.len()
+            ));
+            rows.push(Some((file_index, None)));
+            for (hunk_index, hunk) in file.hunks.iter().enumerate() {
+                let mark = if hunk.included { "[x]" } else { "[ ]" };
+                let span = match hunk.old.len() {
+                    1 => format!("line {}", hunk.start + 1),
+                    count => format!("lines {}-{}", hunk.start + 1, hunk.start + count),
+                };
+                lines.push(format!("  {mark} {span}"));
+                let diff = hunk
+                    .old
+                    .iter()
+                    .map(|line| format!("      -{line}"))
+                    .chain(hunk.new.iter().map(|line| format!("      +{line}")));
+                lines.extend(diff);
+                let hunk_rows = 1 + hunk.old.len() + hunk.new.len();
+                rows.extend(std::iter::repeat_n(
+                    Some((file_index, Some(hunk_index))),
+                    hunk_rows,
+                ));
+            }
+        }
+        replace.rows = rows;
+
+        let mut document = Document::from_text(&lines.join("\n"));
+        document.set_read_only(true);
+        let buffer = replace.buffer;
+        *self.buffer_document_mut(buffer) = document;
+        if buffer == self.current_buffer {
+            self.clamp_cursor();
+        }
+    }
+
+    // In the preview buffer, Space includes or excludes the hunk under the
+    // cursor, or every hunk of a file on its name, and the letters apply or
+    // drop the whole replace.
+    pub(super) fn replace_keypress(&mut self, key: Key) -> bool {
+        let Some(replace) = self.replace.as_mut() else {
+            return false;
+        };
+        if replace.buffer != self.current_buffer || replace.files.is_empty() {
+            return false;
+        }
+        let under_cursor = replace.rows.get(self.cursor_position.y).copied().flatten();
+        match key {
+            Key::Char(' ') => {
+                let Some((file, hunk)) = under_cursor else {
+                    return true;
+                };
+                let hunks = &mut replace.files[file].hunks;
+                if let Some(hunk) = hunk {
+                    hunks[hunk].included = !hunks[hunk].included;
+                } else {
+                    let include = !hunks.iter().all(|hunk| hunk.included);
+                    for hunk in hunks {
+                        hunk.included = include;
+                    }
+                }
+                self.render_replace();
+            }
+            Key::Char('\n') => {
+                let Some((file, hunk)) = under_cursor else {
+                    return true;
+                };
+                let file = &replace.files[file];
+                let path = file.path.clone();
+                let line = hunk.map_or(0, |hunk| file.hunks[hunk].start);
+                if self.jump_to_file(&path, |_| Position { x: 0, y: line }) {
+                    self.clamp_cursor();
+                }
+            }
+            Key::Char('a') => self.apply_replace(false),
+            Key::Char('w') => self.apply_replace(true),
+            Key::Char('q') | Key::Esc => self.finish_replace("Replace cancelled"),
+            _ => return false,
+        }
+
+        true
+    }
+
+    // Leaves the preview buffer behind with `message` in place of the diff.
+    fn finish_replace(&mut self, message: &str) {
+        if let Some(replace) = self.replace.as_mut() {
+            replace.files.clear();
+            replace.title = message.to_string();
+        }
+        self.render_replace();
+        self.set_status_message(message);
+    }
+
+    fn apply_replace(&mut self, on_disk: bool) {
+        let Some(replace) = self.replace.as_mut() else {
+            return;
+        };
+        let files: Vec<FileChanges> = std::mem::take(&mut replace.files)
+            .into_iter()
+            .filter(|file| file.included().next().is_some())
+            .collect();
+        let (applied, problems) = if on_disk {
+            self.replace_on_disk(&files)
+        } else {
+            self.replace_in_buffers(&files)
+        };
+
+        let hunks: usize = files.iter().map(|file| file.included().count()).sum();
+        let mut message = if on_disk {
+            format!("Wrote {hunks} hunks to {} files", applied.len())
+        } else {
+            format!("Applied {hunks} hunks in {} buffers", applied.len())
+        };
+        if !problems.is_empty() {
+            message = format!("{message}; skipped {}", problems.join(", "));
+        }
+        if !applied.is_empty() {
+            self.last_replace = Some(AppliedReplace {
+                on_disk,
+                files: applied,
+            });
+        }
+        self.finish_replace(&message);
+    }
+
+    // Makes the changes in buffers, opening those that are not yet, and
+    // leaves them unsaved. Each buffer gets them as a single undo step.
+    fn replace_in_buffers(
+        &mut self,
+        files: &[FileChanges],
+    ) -> (Vec<(String, String, String)>, Vec<String>) {
+        let preview = self.current_buffer;
+        let mut applied = Vec::new();
+        let mut problems = Vec::new();
+        for file in files {
+            if let Err(err) = self.open_file(&file.path) {
+                problems.push(format!("{} ({err})", file.path));
+                continue;
+            }
+            if self.document.is_read_only() {
+                problems.push(format!("{} (read-only)", file.path));
+                continue;
+            }
+            let before = self.document.contents();
+            let hunks: Vec<_> = file.included().collect();
+            if let Err(err) = replace::apply(&before, &hunks) {
+                problems.push(format!("{} ({err})", file.path));
+                continue;
+            }
+            self.document.commit_edits();
+            for hunk in hunks {
+                for (offset, line) in hunk.new.iter().enumerate() {
+                    self.document.replace_row(hunk.start + offset, line);
+                }
+            }
+            self.document.commit_edits();
+            applied.push((file.path.clone(), before, self.document.contents()));
+        }
+        self.switch_to_buffer(preview);
+
+        (applied, problems)
+    }
+
+    // Writes the changes straight to the files, all of them or none. Files
+    // with unsaved changes in a buffer are left alone; other open buffers
+    // are reloaded.
+    fn replace_on_disk(
+        &mut self,
+        files: &[FileChanges],
+    ) -> (Vec<(String, String, String)>, Vec<String>) {
+        let mut applied = Vec::new();
+        let mut problems = Vec::new();
+        for file in files {
+            let open = self.buffer_index(&file.path);
+            if open.is_some_and(|index| self.buffer_document(index).is_dirty()) {
+                problems.push(format!("{} (unsaved changes)", file.path));
+                continue;
+            }
+            let before = match fs::read_to_string(&file.path) {
+                Ok(before) => before,
+                Err(err) => {
+                    problems.push(format!("{} ({err})", file.path));
+                    continue;
+                }
+            };
+            let hunks: Vec<_> = file.included().collect();
+            match replace::apply(&before, &hunks) {
+                Ok(after) => applied.push((file.path.clone(), before, after)),
+                Err(err) => problems.push(format!("{} ({err})", file.path)),
+            }
+        }
+
+        let writes: Vec<(PathBuf, String)> = applied
+            .iter()
+            .map(|(path, _, after)| (PathBuf::from(path), after.clone()))
+            .collect();
+        if let Err(err) = replace::write_all(&writes) {
+            problems.push(format!("every file (could not write: {err})"));
+            return (Vec::new(), problems);
+        }
+        for (path, _, after) in &applied {
+            self.reload_buffer(path, after);
+        }
+
+        (applied, problems)
+    }
+
+    // Shows `contents`, just written to `path`, in the buffer open on it.
+    fn reload_buffer(&mut self, path: &str, contents: &str) {
+        if let Some(index) = self.buffer_index(path) {
+            let document = self.buffer_document_mut(index);
+            document.commit_edits();
+            document.set_contents(contents);
+            document.commit_edits();
+            document.mark_saved();
+            if index == self.current_buffer {
+                self.clamp_cursor();
+            }
+        }
+    }
+
+    // Puts back what the last replace-in-files changed, in every file that
+    // has not been changed again since.
+    pub(super) fn undo_replace(&mut self) {
+        let Some(last) = self.last_replace.take() else {
+            self.set_status_message("No replace to undo");
+            return;
+        };
+        let mut reverted = Vec::new();
+        let mut changed = Vec::new();
+        for (path, before, after) in last.files {
+            let current = if last.on_disk {
+                fs::read_to_string(&path).ok()
+            } else {
+                self.buffer_index(&path)
+                    .map(|index| self.buffer_document(index).contents())
+            };
+            if current.as_deref() == Some(after.as_str()) {
+                reverted.push((path, before));
+            } else {
+                changed.push(path);
+            }
+        }
+
+        if last.on_disk {
+            let writes: Vec<(PathBuf, String)> = reverted
+                .iter()
+                .map(|(path, before)| (PathBuf::from(path), before.clone()))
+                .collect();
+            if let Err(err) = replace::write_all(&writes) {
+                self.set_status_message(&format!("Could not revert the replace: {err}"));
+                return;
+            }
+            for (path, before) in &reverted {
+                self.reload_buffer(path, before);
+            }
+        } else {
+            for (path, before) in &reverted {
+                if let Some(index) = self.buffer_index(path) {
+                    let document = self.buffer_document_mut(index);
+                    document.commit_edits();
+                    document.set_contents(before);
+                    document.commit_edits();
+                }
+            }
+            self.clamp_cursor();
+        }
+
+        let mut message = format!("Reverted the replace in {} files", reverted.len());
+        if !changed.is_empty() {
+            message = format!(
+                "{message}; {} changed since: {}",
+                changed.len(),
+                changed.join(", ")
+            );
+        }
+        self.set_status_message(&message);
+    }
+}
@@ -0,0 +1,194 @@
+use super::prompt::PromptKind;
+use super::{Buffer, Editor, SelectionMode};
+use crate::pipe::{self, Output};
+use crate::vim::Mode;
+use crate::{Document, Position};
+
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+const SHELL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The text a shell command reads: whole rows, or the characters between two
+// positions.
+enum Input {
+    Lines(Range<usize>),
+    Text(Position, Position),
+}
+
+impl Editor<'_> {
+    pub(super) fn pipe_through_prompt(&mut self) {
+        if let Ok(Some(command)) = self.prompt(PromptKind::Shell, "Pipe through:") {
+            let input = self.shell_input();
+            self.pipe_through(&command, input);
+        }
+    }
+
+    pub(super) fn insert_command_output_prompt(&mut self) {
+        if let Ok(Some(command)) = self.prompt(PromptKind::Shell, "Insert output of:") {
+            self.insert_command_output(&command);
+        }
+    }
+
+    pub(super) fn pipe_to_buffer_prompt(&mut self) {
+        if let Ok(Some(command)) = self.prompt(PromptKind::Shell, "Show output of:") {
+            let input = self.shell_input();
+            self.pipe_to_buffer(&command, &input);
+        }
+    }
+
+    // Replaces rows `range` with what `command` prints when given them.
+    pub(super) fn filter_lines(&mut self, command: &str, range: Range<usize>) {
+        self.pipe_through(command, Input::Lines(range));
+    }
+
+    // Replaces the input with what `command` prints when given it, as one
+    // undo step. Nothing is replaced when the command fails.
+    fn pipe_through(&mut self, command: &str, input: Input) {
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let text = self.input_text(&input);
+        let Some(output) = self.run_shell(command, &text) else {
+            return;
+        };
+
+        self.document.commit_edits();
+        match input {
+            Input::Lines(range) => {
+                let y = range.start;
+                self.document.replace_lines(range, &output);
+                self.cursor_position = Position { x: 0, y };
+            }
+            Input::Text(start, end) => {
+                // Commands end their output with a newline even when the
+                // text they were given did not have one.
+                let output = match output.strip_suffix('\n') {
+                    Some(stripped) if !text.ends_with('\n') => stripped,
+                    _ => &output,
+                };
+                self.document.delete_range(&start, &end);
+                self.document.insert_str(&start, output);
+                self.cursor_position = start;
+            }
+        }
+        self.document.commit_edits();
+        self.clear_selection();
+        self.clamp_cursor();
+        self.set_status_message(&format!("Filtered through {command}"));
+    }
+
+    fn insert_command_output(&mut self, command: &str) {
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let Some(output) = self.run_shell(command, "") else {
+            return;
+        };
+        self.document.commit_edits();
+        self.cursor_position = self.document.insert_str(&self.cursor_position, &output);
+        self.document.commit_edits();
+    }
+
+    // Puts what `command` prints below row `y`, as vim's `:r !` does.
+    pub(super) fn read_command_output(&mut self, command: &str, y: usize) {
+        if self.document.is_read_only() {
+            self.set_status_message(super::READ_ONLY_MESSAGE);
+            return;
+        }
+        let Some(output) = self.run_shell(command, "") else {
+            return;
+        };
+        let below = (y + 1).min(self.document.len());
+        self.document.commit_edits();
+        self.document.replace_lines(below..below, &output);
+        self.document.commit_edits();
+        self.cursor_position = Position { x: 0, y: below };
+        self.clamp_cursor();
+    }
+
+    // Shows what `command` prints, given rows `range`, in a new unnamed
+    // buffer.
+    pub(super) fn pipe_lines_to_buffer(&mut self, command: &str, range: Range<usize>) {
+        self.pipe_to_buffer(command, &Input::Lines(range));
+    }
+
+    fn pipe_to_buffer(&mut self, command: &str, input: &Input) {
+        let text = self.input_text(input);
+        let Some(output) = self.run_shell(command, &text) else {
+            return;
+        };
+        self.clear_selection();
+        let document = Document::from_text(&output);
+        self.buffers
+            .push(Buffer::new(document, Position::default()));
+        self.switch_to_buffer(self.buffers.len() - 1);
+        self.set_status_message(&format!("Output of {command}"));
+    }
+
+    // Drops the mark, or leaves vim's visual mode.
+    fn clear_selection(&mut self) {
+        if self.vim.is_some() {
+            self.vim_set_mode(Mode::Normal);
+        } else {
+            self.selection = None;
+        }
+    }
+
+    // The selection, or the whole buffer when nothing is selected.
+    fn shell_input(&self) -> Input {
+        let Some(selection) = self.selection else {
+            return Input::Lines(0..self.document.len());
+        };
+        let (start, end) = if selection.anchor <= self.cursor_position {
+            (selection.anchor, self.cursor_position)
+        } else {
+            (self.cursor_position, selection.anchor)
+        };
+        match selection.mode {
+            SelectionMode::Line => Input::Lines(start.y..end.y + 1),
+            SelectionMode::Exclusive => Input::Text(start, end),
+            SelectionMode::Inclusive => {
+                let end = Position {
+                    x: end.x + 1,
+                    y: end.y,
+                };
+                Input::Text(start, end)
+            }
+        }
+    }
+
+    fn input_text(&self, input: &Input) -> String {
+        match input {
+            Input::Lines(range) => range.clone().filter_map(|y| self.document.row(y)).fold(
+                String::new(),
+                |mut text, row| {
+                    text.push_str(row.as_str());
+                    text.push('\n');
+                    text
+                },
+            ),
+            Input::Text(start, end) => self.document.text_range(start, end),
+        }
+    }
+
+    // Runs `command` with the shell, returning what it printed. Failures are
+    // reported in the message bar.
+    fn run_shell(&mut self, command: &str, input: &str) -> Option<String> {
+        let args = [String::from("-c"), command.to_string()];
+        match pipe::run("sh", &args, Path::new("."), input, SHELL_TIMEOUT) {
+            Ok(Output { status, stdout, .. }) if status.success() => Some(stdout),
+            Ok(output) => {
+                self.set_status_message(&format!("{command}: {}", output.error_message()));
+                None
+            }
+            Err(err) => {
+                self.set_status_message(&format!("Could not run {command}: {err}"));
+                None
+            }
+        }
+    }
+}
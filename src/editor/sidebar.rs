@@ -0,0 +1,232 @@
+use super::prompt::PromptKind;
+use super::Editor;
+use crate::explorer::Explorer;
+use crate::Terminal;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use termion::event::Key;
+
+pub(super) const SIDEBAR_WIDTH: usize = 30;
+
+impl Editor<'_> {
+    pub(super) fn toggle_explorer(&mut self) {
+        if self.explorer.take().is_some() {
+            self.explorer_focused = false;
+        } else {
+            self.focus_explorer();
+        }
+    }
+
+    // Opens the explorer on the current file's directory, or the working
+    // directory for an unnamed buffer, and moves the keyboard focus to it.
+    pub(super) fn focus_explorer(&mut self) {
+        if self.explorer.is_none() {
+            let file = self
+                .document
+                .filename
+                .as_ref()
+                .and_then(|filename| std::path::absolute(filename).ok());
+            let root = file
+                .as_ref()
+                .and_then(|file| file.parent().map(Path::to_path_buf))
+                .or_else(|| env::current_dir().ok())
+                .unwrap_or_else(|| PathBuf::from("."));
+            let mut explorer = Explorer::new(root);
+            if let Some(file) = file {
+                explorer.select_path(&file);
+            }
+            self.explorer = Some(explorer);
+        }
+        self.explorer_focused = true;
+    }
+
+    // Handles a key while the explorer has the focus. Keys it does not use,
+    // other than plain characters, fall through to the usual bindings.
+    pub(super) fn explorer_keypress(&mut self, key: Key) -> bool {
+        let Some(explorer) = self.explorer.as_mut() else {
+            self.explorer_focused = false;
+            return false;
+        };
+        match key {
+            Key::Up | Key::Char('k') | Key::Ctrl('p') => explorer.select_previous(),
+            Key::Down | Key::Char('j') | Key::Ctrl('n') => explorer.select_next(),
+            Key::Right | Key::Char('l') => explorer.expand(),
+            Key::Left | Key::Char('h') => explorer.collapse(),
+            Key::Char('\n') => self.explorer_open(false),
+            Key::Char('o') => self.explorer_open(true),
+            Key::Char('a') => self.explorer_create(),
+            Key::Char('r') => self.explorer_rename(),
+            Key::Char('d') => self.explorer_delete(),
+            Key::Char('i') => {
+                let message = if explorer.toggle_hide_ignored() {
+                    "Hiding ignored files"
+                } else {
+                    "Showing ignored files"
+                };
+                self.set_status_message(message);
+            }
+            Key::Char('R') => explorer.refresh(),
+            Key::Char('q') => self.toggle_explorer(),
+            Key::Esc | Key::Char('\t') => self.explorer_focused = false,
+            Key::Char(_) => (),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn explorer_open(&mut self, new_buffer: bool) {
+        let Some(entry) = self.explorer.as_ref().and_then(Explorer::selected) else {
+            return;
+        };
+        if entry.is_dir {
+            if let Some(explorer) = self.explorer.as_mut() {
+                explorer.toggle();
+            }
+            return;
+        }
+        let path = display_path(&entry.path);
+        let opened = if new_buffer {
+            self.open_file(&path)
+        } else {
+            self.open_file_here(&path)
+        };
+        match opened {
+            Ok(()) => self.explorer_focused = false,
+            Err(err) => self.set_status_message(&format!("Could not open {path}: {err}")),
+        }
+    }
+
+    // A name ending in '/' creates a directory instead of a file.
+    fn explorer_create(&mut self) {
+        let Some(directory) = self.explorer.as_ref().map(Explorer::target_directory) else {
+            return;
+        };
+        let prompt = format!("New file in {}/:", display_path(&directory));
+        let Ok(Some(name)) = self.prompt(PromptKind::Path, &prompt) else {
+            return;
+        };
+        let path = directory.join(name.trim());
+        let created = if name.ends_with('/') {
+            fs::create_dir_all(&path)
+        } else {
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)
+                        .map(drop)
+                })
+        };
+        match created {
+            Ok(()) => {
+                self.set_status_message(&format!("Created {}", display_path(&path)));
+                if let Some(explorer) = self.explorer.as_mut() {
+                    explorer.refresh();
+                    explorer.select_path(&path);
+                }
+            }
+            Err(err) => {
+                let name = display_path(&path);
+                self.set_status_message(&format!("Could not create {name}: {err}"));
+            }
+        }
+    }
+
+    fn explorer_rename(&mut self) {
+        let Some(old) = self.explorer_selected_path() else {
+            return;
+        };
+        let old_name = display_path(&old);
+        let Ok(Some(new_name)) = self.prompt_prefilled(PromptKind::Path, "Rename to:", &old_name)
+        else {
+            return;
+        };
+        let Some(new_name) = self.prepare_destination(new_name.trim()) else {
+            return;
+        };
+        match fs::rename(&old, &new_name) {
+            Ok(()) => {
+                self.rename_buffers(&old_name, &new_name);
+                self.set_status_message(&format!("Renamed {old_name} to {new_name}"));
+                if let (Some(explorer), Ok(new)) =
+                    (self.explorer.as_mut(), std::path::absolute(&new_name))
+                {
+                    explorer.refresh();
+                    explorer.select_path(&new);
+                }
+            }
+            Err(err) => self.set_status_message(&format!("Could not rename {old_name}: {err}")),
+        }
+    }
+
+    fn explorer_delete(&mut self) {
+        let Some(path) = self.explorer_selected_path() else {
+            return;
+        };
+        let name = display_path(&path);
+        let is_dir = path.is_dir();
+        let question = if is_dir {
+            format!("Delete {name}/ and everything in it?")
+        } else {
+            format!("Delete {name}?")
+        };
+        if !self.confirm(&question) {
+            self.set_status_message("Cancelled");
+            return;
+        }
+        let deleted = if is_dir {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match deleted {
+            Ok(()) => {
+                self.set_status_message(&format!("Deleted {name}"));
+                if let Some(explorer) = self.explorer.as_mut() {
+                    explorer.refresh();
+                }
+            }
+            Err(err) => self.set_status_message(&format!("Could not delete {name}: {err}")),
+        }
+    }
+
+    fn explorer_selected_path(&self) -> Option<PathBuf> {
+        self.explorer
+            .as_ref()
+            .and_then(Explorer::selected)
+            .map(|entry| entry.path.clone())
+    }
+
+    pub(super) fn draw_sidebar_line(&self, line: &str, selected: bool) {
+        if selected {
+            if self.explorer_focused {
+                Terminal::set_bg_color(self.config.status_line_bg_color);
+                Terminal::set_fg_color(self.config.status_line_fg_color);
+            } else {
+                Terminal::invert_colors();
+            }
+        }
+        print!("{line:<0$}", SIDEBAR_WIDTH - 1);
+        Terminal::reset_colors();
+        Terminal::reset_bg_color();
+        Terminal::reset_fg_color();
+        print!("│");
+    }
+}
+
+// Paths are shown, and buffers named, relative to the working directory
+// when they are inside it.
+pub(super) fn display_path(path: &Path) -> String {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
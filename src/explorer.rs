@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Entry {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+// A tree of the files under `root`, flattened into the rows shown in the
+// sidebar. Only expanded directories have their children listed.
+pub struct Explorer {
+    root: PathBuf,
+    entries: Vec<Entry>,
+    expanded: HashSet<PathBuf>,
+    selected: usize,
+    hide_ignored: bool,
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        let mut explorer = Self {
+            root,
+            entries: Vec::new(),
+            expanded: HashSet::new(),
+            selected: 0,
+            hide_ignored: false,
+        };
+        explorer.refresh();
+
+        explorer
+    }
+
+    pub fn refresh(&mut self) {
+        let selected = self.selected().map(|entry| entry.path.clone());
+        let mut entries = Vec::new();
+        self.list(&self.root, 0, &mut entries);
+        self.entries = entries;
+        self.selected = 0;
+        if let Some(path) = selected {
+            self.select_path(&path);
+        }
+    }
+
+    fn list(&self, directory: &Path, depth: usize, entries: &mut Vec<Entry>) {
+        let mut children = if self.hide_ignored {
+            unignored_children(directory)
+        } else {
+            fs::read_dir(directory)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .collect()
+        };
+        // Directories first, then files, each alphabetically.
+        children.sort_by_key(|path| (!path.is_dir(), path.file_name().map(ToOwned::to_owned)));
+
+        for path in children {
+            let is_dir = path.is_dir();
+            let expanded = is_dir && self.expanded.contains(&path);
+            entries.push(Entry {
+                path: path.clone(),
+                depth,
+                is_dir,
+            });
+            if expanded {
+                self.list(&path, depth + 1, entries);
+            }
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    // Selects `path`, expanding the directories above it so it is listed.
+    pub fn select_path(&mut self, path: &Path) {
+        let mut expanded_any = false;
+        for ancestor in path.ancestors().skip(1) {
+            if ancestor == self.root || !ancestor.starts_with(&self.root) {
+                break;
+            }
+            expanded_any |= self.expanded.insert(ancestor.to_path_buf());
+        }
+        if expanded_any {
+            self.refresh();
+        }
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        }
+    }
+
+    pub fn expand(&mut self) {
+        if let Some(entry) = self.selected().filter(|entry| entry.is_dir) {
+            let path = entry.path.clone();
+            if self.expanded.insert(path) {
+                self.refresh();
+            }
+        }
+    }
+
+    // Collapses the selected directory, or moves up to the enclosing one.
+    pub fn collapse(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let path = entry.path.clone();
+        if entry.is_dir && self.expanded.remove(&path) {
+            self.refresh();
+        } else if let Some(parent) = path.parent().filter(|parent| *parent != self.root) {
+            let parent = parent.to_path_buf();
+            self.select_path(&parent);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let Some(entry) = self.selected().filter(|entry| entry.is_dir) else {
+            return;
+        };
+        if self.expanded.contains(&entry.path) {
+            self.collapse();
+        } else {
+            self.expand();
+        }
+    }
+
+    pub fn toggle_hide_ignored(&mut self) -> bool {
+        self.hide_ignored = !self.hide_ignored;
+        self.refresh();
+
+        self.hide_ignored
+    }
+
+    // Where a new file goes: into the selected directory, or next to the
+    // selected file.
+    pub fn target_directory(&self) -> PathBuf {
+        match self.selected() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map_or_else(|| self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+
+    // A heading naming the root, then at most `height - 1` entries scrolled
+    // so the selection is visible, along with the index of the selected line.
+    pub fn lines(&self, height: usize, width: usize) -> (Vec<String>, usize) {
+        let name = self.root.file_name().map_or_else(
+            || self.root.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let mut lines = vec![fit(&format!(" {name}/"), width)];
+        let rows = height.saturating_sub(1);
+        let first = (self.selected + 1).saturating_sub(rows);
+        for entry in self.entries.iter().skip(first).take(rows) {
+            let marker = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                _ => "  ",
+            };
+            let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+            let slash = if entry.is_dir { "/" } else { "" };
+            let indent = "  ".repeat(entry.depth);
+            lines.push(fit(&format!(" {indent}{marker}{name}{slash}"), width));
+        }
+
+        (lines, self.selected - first + 1)
+    }
+}
+
+fn fit(line: &str, width: usize) -> String {
+    let mut line: String = line.chars().take(width).collect();
+    let len = line.chars().count();
+    line.push_str(&" ".repeat(width - len));
+
+    line
+}
+
+// The children of `directory` that git would not ignore, taking the
+// .gitignore files of its parents into account.
+fn unignored_children(directory: &Path) -> Vec<PathBuf> {
+    ignore::WalkBuilder::new(directory)
+        .max_depth(Some(1))
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .flatten()
+        .filter(|entry| entry.depth() == 1)
+        .map(ignore::DirEntry::into_path)
+        .collect()
+}
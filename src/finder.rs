@@ -0,0 +1,181 @@
+use crate::fuzzy::fuzzy_match;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+const PREVIEW_LINES: usize = 200;
+// How many walked paths to take per poll, so typing stays responsive while a
+// large tree is still being listed.
+const POLL_BATCH: usize = 20_000;
+
+struct Match {
+    index: usize,
+    score: i64,
+    positions: Vec<usize>,
+}
+
+// Picks a file under `root` by fuzzy matching its path as the walk finds it.
+pub struct Finder {
+    root: PathBuf,
+    paths: Vec<String>,
+    walk: Option<Receiver<PathBuf>>,
+    query: String,
+    matches: Vec<Match>,
+    selected: usize,
+    preview: Vec<String>,
+    pub accepted: bool,
+}
+
+impl Finder {
+    pub fn new(root: PathBuf, walk: Receiver<PathBuf>) -> Self {
+        Self {
+            root,
+            paths: Vec::new(),
+            walk: Some(walk),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            preview: Vec::new(),
+            accepted: false,
+        }
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.walk.is_some()
+    }
+
+    // Takes in the paths found since the last call, returning whether any
+    // arrived.
+    pub fn poll(&mut self) -> bool {
+        let Some(walk) = &self.walk else {
+            return false;
+        };
+        let first_new = self.paths.len();
+        let mut finished = false;
+        while self.paths.len() - first_new < POLL_BATCH {
+            match walk.try_recv() {
+                Ok(path) => {
+                    let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+                    self.paths.push(relative.to_string_lossy().into_owned());
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        if finished {
+            self.walk = None;
+        }
+        let found = self.paths.len() > first_new;
+        if found {
+            self.match_paths(first_new);
+        }
+
+        found || finished
+    }
+
+    pub fn filter(&mut self, query: &str) {
+        if query == self.query {
+            return;
+        }
+        query.clone_into(&mut self.query);
+        self.matches.clear();
+        self.selected = 0;
+        self.match_paths(0);
+    }
+
+    fn match_paths(&mut self, from: usize) {
+        let selected = self.matches.get(self.selected).map(|found| found.index);
+        for (index, path) in self.paths.iter().enumerate().skip(from) {
+            if let Some(found) = fuzzy_match(&self.query, path) {
+                self.matches.push(Match {
+                    index,
+                    score: found.score,
+                    positions: found.positions,
+                });
+            }
+        }
+        let paths = &self.paths;
+        self.matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(paths[a.index].len().cmp(&paths[b.index].len()))
+                .then(paths[a.index].cmp(&paths[b.index]))
+        });
+        // Keep the same file highlighted as new paths arrive.
+        self.selected = selected
+            .and_then(|index| self.matches.iter().position(|found| found.index == index))
+            .unwrap_or(0);
+        self.load_preview();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+            self.load_preview();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+            self.load_preview();
+        }
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.matches
+            .get(self.selected)
+            .map(|found| self.root.join(&self.paths[found.index]))
+    }
+
+    fn load_preview(&mut self) {
+        self.preview = self
+            .selected_path()
+            .map(|path| preview(&path))
+            .unwrap_or_default();
+    }
+
+    pub fn preview(&self) -> &[String] {
+        &self.preview
+    }
+
+    // "matches/total", with a note while the walk is still going.
+    pub fn summary(&self) -> String {
+        let walking = if self.is_walking() { " (searching...)" } else { "" };
+        format!("{}/{}{walking}", self.matches.len(), self.paths.len())
+    }
+
+    // At most `height` paths, scrolled so the selection is visible, each with
+    // the character positions that matched the query. Also returns the index
+    // of the selected line.
+    pub fn lines(&self, height: usize) -> (Vec<(&str, &[usize])>, usize) {
+        let first = (self.selected + 1).saturating_sub(height);
+        let lines = self
+            .matches
+            .iter()
+            .skip(first)
+            .take(height)
+            .map(|found| (self.paths[found.index].as_str(), found.positions.as_slice()))
+            .collect();
+
+        (lines, self.selected - first)
+    }
+}
+
+fn preview(path: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read(path) else {
+        return vec![String::from("[could not read file]")];
+    };
+    if contents.contains(&0) {
+        return vec![String::from("[binary file]")];
+    }
+    String::from_utf8_lossy(&contents)
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| line.replace('\t', "    "))
+        .collect()
+}
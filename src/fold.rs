@@ -0,0 +1,112 @@
+use crate::{Document, Row};
+
+use std::collections::BTreeMap;
+
+// Rows that can be folded away under row `start`, which stays in view as the
+// fold's summary. The hidden rows are `start + 1..end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    pub fn hides(&self, y: usize) -> bool {
+        self.start < y && y < self.end
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.end - self.start - 1
+    }
+}
+
+// The folds of `document`, ordered by their first row. Ranges from a
+// language server, as first and last rows, are used when there are any;
+// otherwise blocks between brackets, and where none starts, deeper indented
+// lines.
+pub fn folds(document: &Document, ranges: &[(usize, usize)]) -> Vec<Fold> {
+    let mut folds = BTreeMap::new();
+    if ranges.is_empty() {
+        folds.extend(indentation_folds(document));
+        folds.extend(bracket_folds(document));
+    } else {
+        folds.extend(ranges.iter().map(|&(first, last)| (first, last + 1)));
+    }
+    folds
+        .into_iter()
+        .filter(|&(start, end)| end > start + 1 && end <= document.len())
+        .map(|(start, end)| Fold { start, end })
+        .collect()
+}
+
+// Each line followed by more deeply indented ones folds them, leaving out
+// blank lines after the last of them.
+fn indentation_folds(document: &Document) -> Vec<(usize, usize)> {
+    let mut folds = Vec::new();
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last = 0;
+    for y in 0..document.len() {
+        let Some(line) = document.row(y).map(Row::as_str) else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        while let Some(&(open_indent, start)) = open.last() {
+            if open_indent < indent {
+                break;
+            }
+            open.pop();
+            folds.push((start, last + 1));
+        }
+        open.push((indent, y));
+        last = y;
+    }
+    folds.extend(open.into_iter().map(|(_, start)| (start, last + 1)));
+
+    folds
+}
+
+// Each line opening a bracket closed on a later line folds the lines up to
+// the closing one, and that one too when it holds nothing else. Brackets in
+// strings and line comments are skipped.
+fn bracket_folds(document: &Document) -> Vec<(usize, usize)> {
+    let mut folds: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut open: Vec<usize> = Vec::new();
+    for y in 0..document.len() {
+        let Some(line) = document.row(y).map(Row::as_str) else {
+            break;
+        };
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut previous = ' ';
+        for c in line.chars() {
+            if in_string {
+                in_string = escaped || c != '"';
+                escaped = !escaped && c == '\\';
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '/' if previous == '/' => break,
+                '{' | '(' | '[' => open.push(y),
+                '}' | ')' | ']' => {
+                    if let Some(start) = open.pop().filter(|&start| start < y) {
+                        let closing_only = line
+                            .trim()
+                            .chars()
+                            .all(|c| matches!(c, '}' | ')' | ']' | ';' | ','));
+                        let end = if closing_only { y + 1 } else { y };
+                        let fold = folds.entry(start).or_insert(end);
+                        *fold = (*fold).max(end);
+                    }
+                }
+                _ => (),
+            }
+            previous = c;
+        }
+    }
+
+    folds.into_iter().collect()
+}
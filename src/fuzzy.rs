@@ -0,0 +1,91 @@
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 24;
+const BOUNDARY_BONUS: i64 = 20;
+const FIRST_CHAR_BONUS: i64 = 12;
+const GAP_PENALTY: i64 = 1;
+
+pub struct FuzzyMatch {
+    pub score: i64,
+    // The indices of the matched characters in the candidate.
+    pub positions: Vec<usize>,
+}
+
+// Matches `pattern` as a case-insensitive subsequence of `candidate`, greedily
+// preferring word starts so that "gl" ranks "goto-line" above "toggle".
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut start = 0;
+    for (index, wanted) in pattern.iter().enumerate() {
+        let remaining = &pattern[index..];
+        let found = (start..chars.len())
+            .filter(|&i| lower(chars[i]) == *wanted)
+            .find(|&i| is_boundary(&chars, i) && is_subsequence(remaining, &chars[i..]))
+            .or_else(|| (start..chars.len()).find(|&i| lower(chars[i]) == *wanted))?;
+        positions.push(found);
+        start = found + 1;
+    }
+
+    Some(FuzzyMatch {
+        score: score(&chars, &positions),
+        positions,
+    })
+}
+
+fn score(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &position in positions {
+        score += MATCH_SCORE;
+        if position == 0 {
+            score += FIRST_CHAR_BONUS;
+        }
+        if is_boundary(chars, position) {
+            score += BOUNDARY_BONUS;
+        }
+        match previous {
+            Some(previous) if previous + 1 == position => score += CONSECUTIVE_BONUS,
+            Some(previous) => score -= GAP_PENALTY * to_i64(position - previous - 1),
+            None => score -= GAP_PENALTY * to_i64(position),
+        }
+        previous = Some(position);
+    }
+    // Prefer shorter candidates when everything else is equal.
+    score - to_i64(chars.len()) / 4
+}
+
+fn is_subsequence(pattern: &[char], chars: &[char]) -> bool {
+    let mut chars = chars.iter();
+    pattern
+        .iter()
+        .all(|wanted| chars.any(|c| lower(*c) == *wanted))
+}
+
+fn is_boundary(chars: &[char], index: usize) -> bool {
+    if index == 0 {
+        return true;
+    }
+    let previous = chars[index - 1];
+    let current = chars[index];
+    !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn to_i64(value: usize) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
@@ -0,0 +1,118 @@
+use crate::pipe;
+
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The commit that last touched a line, as `git blame` reports it.
+#[derive(Clone, Debug, Default)]
+pub struct Commit {
+    pub hash: String,
+    pub author: String,
+    // Seconds since the epoch, and the author's offset from UTC in seconds.
+    pub time: i64,
+    pub offset: i32,
+}
+
+impl Commit {
+    // Lines that differ from every commit are blamed on the all-zero hash.
+    pub fn is_committed(&self) -> bool {
+        self.hash.chars().any(|c| c != '0')
+    }
+
+    pub fn short_hash(&self) -> &str {
+        self.hash.get(..7).unwrap_or(&self.hash)
+    }
+
+    // The author date in the author's time zone, as YYYY-MM-DD.
+    pub fn date(&self) -> String {
+        FixedOffset::east_opt(self.offset)
+            .zip(NaiveDateTime::from_timestamp_opt(self.time, 0))
+            .map(|(offset, time)| {
+                offset
+                    .from_utc_datetime(&time)
+                    .format("%Y-%m-%d")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Runs git in the directory of `path` and returns its output, or None when
+// git fails, such as outside a repository.
+fn git(path: &Path, args: &[&str], input: &str) -> Option<String> {
+    let absolute = std::path::absolute(path).ok()?;
+    let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+    let output = pipe::run("git", &args, absolute.parent()?, input, GIT_TIMEOUT).ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+// The text of the file at `path` as committed at HEAD, or None when it is
+// not tracked.
+pub fn head_text(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    git(path, &["show", &format!("HEAD:./{name}")], "")
+}
+
+// The commit behind each line of `contents`, the file at `path` as it is
+// being edited, or None when the file is not tracked.
+pub fn blame(path: &Path, contents: &str) -> Option<Vec<Commit>> {
+    let name = path.file_name()?.to_str()?;
+    let output = git(
+        path,
+        &["blame", "--porcelain", "--contents", "-", "--", name],
+        contents,
+    )?;
+
+    // Each line starts with a header naming its commit, followed the first
+    // time that commit appears by what is known about it, then the line's
+    // text after a tab.
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            if let Some(commit) = current.take().and_then(|hash| commits.get(&hash)) {
+                lines.push(commit.clone());
+            }
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let Some(hash) = &current else {
+            let commit = commits.entry(key.to_string()).or_default();
+            commit.hash = key.to_string();
+            current = Some(key.to_string());
+            continue;
+        };
+        let Some(commit) = commits.get_mut(hash) else {
+            continue;
+        };
+        match key {
+            "author" => commit.author = value.to_string(),
+            "author-time" => commit.time = value.parse().unwrap_or(0),
+            "author-tz" => commit.offset = parse_offset(value),
+            _ => (),
+        }
+    }
+
+    Some(lines)
+}
+
+// The full description of commit `hash`, with its author, date and message,
+// from the repository holding `path`.
+pub fn commit_message(path: &Path, hash: &str) -> Option<String> {
+    git(path, &["show", "--no-patch", "--format=medium", hash], "")
+}
+
+// Seconds east of UTC for an offset written as +HHMM or -HHMM.
+fn parse_offset(offset: &str) -> i32 {
+    let (sign, digits) = match offset.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, offset.trim_start_matches('+')),
+    };
+    let value: i32 = digits.parse().unwrap_or(0);
+    sign * (value / 100 * 3600 + value % 100 * 60)
+}
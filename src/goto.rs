@@ -0,0 +1,58 @@
+pub struct GotoTarget {
+    pub path: Option<String>,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+// Parses `line`, `line:col`, `path:line[:col]` (as printed by compilers),
+// `+N`/`-N` relative to `current`, or `N%` of the document. Lines and columns
+// are typed 1-based and returned 0-based; clamping is left to the caller.
+pub fn parse_goto(input: &str, current: usize, line_count: usize) -> Result<GotoTarget, String> {
+    let input = input.trim().trim_end_matches(':');
+    let number = |text: &str| {
+        text.parse::<usize>()
+            .map_err(|_| format!("Invalid line or column: '{text}'"))
+    };
+    let target = |line: usize| GotoTarget {
+        path: None,
+        line,
+        column: None,
+    };
+
+    if let Some(percent) = input.strip_suffix('%') {
+        let percent = number(percent.trim())?.min(100);
+        let line = (line_count * percent + 50) / 100;
+        return Ok(target(line.saturating_sub(1)));
+    }
+    if let Some(offset) = input.strip_prefix('+') {
+        return Ok(target(current.saturating_add(number(offset)?)));
+    }
+    if let Some(offset) = input.strip_prefix('-') {
+        return Ok(target(current.saturating_sub(number(offset)?)));
+    }
+
+    let mut parts: Vec<&str> = input.rsplitn(3, ':').collect();
+    parts.reverse();
+    let numeric = |text: &&str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    let (path, line, column) = match parts[..] {
+        [path, line, column] if numeric(&line) && numeric(&column) => {
+            (Some(path), line, Some(column))
+        }
+        [path, line, column] if numeric(&column) => {
+            (Some(&input[..=(path.len() + line.len())]), column, None)
+        }
+        [line, column] if numeric(&line) && numeric(&column) => (None, line, Some(column)),
+        [path, line] if numeric(&line) => (Some(path), line, None),
+        [line] => (None, line, None),
+        _ => return Err(format!("Invalid location: '{input}'")),
+    };
+
+    Ok(GotoTarget {
+        path: path.filter(|path| !path.is_empty()).map(str::to_string),
+        line: number(line)?.saturating_sub(1),
+        column: column
+            .map(number)
+            .transpose()?
+            .map(|column| column.saturating_sub(1)),
+    })
+}
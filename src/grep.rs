@@ -0,0 +1,52 @@
+use crate::project;
+
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub struct GrepMatch {
+    pub path: PathBuf,
+    // Zero-based, with the column counted in graphemes like the cursor's.
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+// Searches the files under `root` that git would not ignore for `regex` on a
+// background thread, sending the first match on each line as it is found.
+// Dropping the receiver cancels the search.
+pub fn search(root: &Path, regex: Regex) -> Receiver<GrepMatch> {
+    let (sender, receiver) = mpsc::channel();
+    let files = project::walk_files(root);
+    thread::spawn(move || {
+        for path in files {
+            let Ok(contents) = fs::read(&path) else {
+                continue;
+            };
+            // Skip binary files, as grep does.
+            if contents.contains(&0) {
+                continue;
+            }
+            let contents = String::from_utf8_lossy(&contents);
+            for (line, text) in contents.lines().enumerate() {
+                let Some(found) = regex.find(text) else {
+                    continue;
+                };
+                let grep_match = GrepMatch {
+                    path: path.clone(),
+                    line,
+                    column: text[..found.start()].graphemes(true).count(),
+                    text: text.trim_end_matches('\r').to_string(),
+                };
+                if sender.send(grep_match).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    receiver
+}
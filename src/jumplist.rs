@@ -0,0 +1,54 @@
+use crate::Position;
+
+const CAPACITY: usize = 100;
+
+// A place the cursor jumped away from: a position in one of the editor's
+// buffers, by index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Jump {
+    pub buffer: usize,
+    pub position: Position,
+}
+
+// Places the cursor jumped away from, across buffers, navigated like vim's
+// Ctrl-O and Ctrl-I. `index` equals `entries.len()` while not walking the
+// list.
+#[derive(Default)]
+pub struct JumpList {
+    entries: Vec<Jump>,
+    index: usize,
+}
+
+impl JumpList {
+    pub fn push(&mut self, from: Jump) {
+        self.entries.truncate(self.index);
+        self.entries
+            .retain(|entry| entry.buffer != from.buffer || entry.position.y != from.position.y);
+        self.entries.push(from);
+        if self.entries.len() > CAPACITY {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        // Remember where the walk started so that forward can return to it.
+        if self.index == self.entries.len() {
+            self.push(current);
+            self.index -= 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        self.entries.get(self.index).copied()
+    }
+
+    pub fn forward(&mut self) -> Option<Jump> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        self.entries.get(self.index).copied()
+    }
+}
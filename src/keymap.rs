@@ -0,0 +1,369 @@
+use crate::Command;
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use termion::event::Key;
+
+const DEFAULT_BINDINGS: &[(&str, Command)] = &[
+    ("Ctrl-Q", Command::Quit),
+    ("Ctrl-S", Command::Save),
+    ("Ctrl-P", Command::CommandPalette),
+    ("Backspace", Command::DeleteBackward),
+    ("Delete", Command::DeleteForward),
+    ("Up", Command::MoveUp),
+    ("Down", Command::MoveDown),
+    ("Left", Command::MoveLeft),
+    ("Right", Command::MoveRight),
+    ("PageUp", Command::PageUp),
+    ("PageDown", Command::PageDown),
+    ("Home", Command::SmartLineStart),
+    ("End", Command::LineEnd),
+    ("Ctrl-Left", Command::WordBackward),
+    ("Ctrl-Right", Command::WordForward),
+    ("Ctrl-Up", Command::ParagraphBackward),
+    ("Ctrl-Down", Command::ParagraphForward),
+    ("Ctrl-Home", Command::DocumentStart),
+    ("Ctrl-End", Command::DocumentEnd),
+    ("Ctrl-G", Command::GotoLine),
+    ("Ctrl-O", Command::JumpBack),
+    ("Alt-Left", Command::JumpBack),
+    ("Alt-Right", Command::JumpForward),
+    ("Ctrl-T", Command::FindFile),
+    ("Alt-f", Command::Grep),
+    ("Alt-r", Command::ReplaceInFiles),
+    ("Alt-h", Command::Hover),
+    ("F12", Command::GotoDefinition),
+    ("Alt-?", Command::FindReferences),
+    ("Ctrl-Space", Command::Complete),
+    ("F5", Command::Build),
+    ("F8", Command::NextError),
+    ("F7", Command::PreviousError),
+    ("Alt-|", Command::PipeThrough),
+    ("Alt-!", Command::InsertCommandOutput),
+    ("Alt-n", Command::NextHunk),
+    ("Alt-p", Command::PreviousHunk),
+    ("Alt-o", Command::DiffOtherSide),
+    ("Alt-z", Command::ToggleFold),
+    ("Ctrl-Z", Command::Undo),
+    ("Ctrl-Y", Command::Redo),
+    ("Ctrl-B", Command::ToggleExplorer),
+    ("Alt-e", Command::FocusExplorer),
+    ("Alt-.", Command::NextBuffer),
+    ("Alt-,", Command::PreviousBuffer),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Standard,
+    Vim,
+    Emacs,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "standard" => Ok(Preset::Standard),
+            "vim" => Ok(Preset::Vim),
+            "emacs" => Ok(Preset::Emacs),
+            _ => Err(invalid(format!(
+                "Unknown keymap preset '{name}', expected \"standard\", \"vim\" or \"emacs\""
+            ))),
+        }
+    }
+}
+
+const EMACS_BINDINGS: &[(&str, Command)] = &[
+    ("Ctrl-A", Command::LineStart),
+    ("Ctrl-E", Command::LineEnd),
+    ("Ctrl-F", Command::MoveRight),
+    ("Ctrl-B", Command::MoveLeft),
+    ("Ctrl-N", Command::MoveDown),
+    ("Ctrl-P", Command::MoveUp),
+    ("Ctrl-V", Command::PageDown),
+    ("Alt-v", Command::PageUp),
+    ("Alt-f", Command::WordForward),
+    ("Alt-b", Command::WordBackward),
+    ("Ctrl-D", Command::DeleteForward),
+    ("Ctrl-K", Command::KillLine),
+    ("Ctrl-W", Command::KillRegion),
+    ("Alt-w", Command::CopyRegion),
+    ("Ctrl-Y", Command::Yank),
+    ("Alt-y", Command::YankPop),
+    ("Ctrl-Space", Command::SetMark),
+    ("Ctrl-G", Command::Cancel),
+    ("Ctrl-X Ctrl-F", Command::OpenFile),
+    ("Ctrl-X Ctrl-W", Command::SaveAs),
+    ("Ctrl-X d", Command::ToggleExplorer),
+    ("Ctrl-X p f", Command::FindFile),
+    ("Ctrl-X p g", Command::Grep),
+    ("Ctrl-X p r", Command::ReplaceInFiles),
+    ("Alt-.", Command::GotoDefinition),
+    ("Alt-/", Command::Complete),
+    ("Ctrl-X `", Command::NextError),
+    ("Alt-g n", Command::NextError),
+    ("Alt-g p", Command::PreviousError),
+    ("Ctrl-X v ]", Command::NextHunk),
+    ("Ctrl-X v [", Command::PreviousHunk),
+    ("Ctrl-X v =", Command::ShowHunk),
+    ("Ctrl-X v n", Command::RevertHunk),
+    ("Ctrl-X v g", Command::ToggleBlame),
+    ("Ctrl-X v l", Command::ShowCommit),
+    ("Ctrl-X o", Command::DiffOtherSide),
+    ("Ctrl-C ^ n", Command::NextConflict),
+    ("Ctrl-C ^ p", Command::PreviousConflict),
+    ("Ctrl-C ^ u", Command::AcceptOurs),
+    ("Ctrl-C ^ l", Command::AcceptTheirs),
+    ("Ctrl-C ^ a", Command::AcceptBoth),
+    ("Ctrl-C ^ b", Command::AcceptBase),
+    ("Ctrl-C @ Ctrl-C", Command::ToggleFold),
+    ("Ctrl-C @ Ctrl-H", Command::Fold),
+    ("Ctrl-C @ Ctrl-S", Command::Unfold),
+    ("Ctrl-C @ Ctrl-T", Command::FoldAll),
+    ("Ctrl-C @ Ctrl-A", Command::UnfoldAll),
+    ("Ctrl-X u", Command::Undo),
+    // Ctrl-/ and Ctrl-_ both reach us as Ctrl-7.
+    ("Ctrl-7", Command::Undo),
+    ("Ctrl-X Ctrl-Q", Command::ToggleReadOnly),
+    ("Ctrl-X Right", Command::NextBuffer),
+    ("Ctrl-X Left", Command::PreviousBuffer),
+    ("Alt-g g", Command::GotoLine),
+    ("Alt-g Alt-g", Command::GotoLine),
+    ("Alt-x", Command::CommandPalette),
+    ("Ctrl-X Ctrl-S", Command::Save),
+    ("Ctrl-X Ctrl-C", Command::Quit),
+];
+
+// Normal mode keys are handled by the vim module; these are the chords it
+// leaves to the keymap.
+const VIM_BINDINGS: &[(&str, Command)] = &[
+    ("Ctrl-R", Command::Redo),
+    // Insert mode completion, as in vim.
+    ("Ctrl-N", Command::Complete),
+    ("Ctrl-W w", Command::DiffOtherSide),
+    ("Ctrl-W Ctrl-W", Command::DiffOtherSide),
+];
+
+pub enum Lookup {
+    Command(Command),
+    Prefix,
+    Unbound,
+}
+
+#[derive(Debug, Default)]
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Command>,
+}
+
+impl Keymap {
+    pub fn for_preset(preset: Preset) -> Self {
+        let preset_bindings = match preset {
+            Preset::Standard => &[],
+            Preset::Vim => VIM_BINDINGS,
+            Preset::Emacs => EMACS_BINDINGS,
+        };
+        let mut keymap = Self::default();
+        for (chord, command) in DEFAULT_BINDINGS.iter().chain(preset_bindings) {
+            let keys = parse_chord(chord).expect("Invalid default key binding");
+            keymap.bind(keys, *command);
+        }
+
+        keymap
+    }
+
+    pub fn bind(&mut self, chord: Vec<Key>, command: Command) {
+        self.bindings.insert(chord, command);
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &HashMap<String, String>) -> Result<(), Error> {
+        for (chord, name) in overrides {
+            let keys = parse_chord(chord)?;
+            let command = Command::from_name(name).ok_or_else(|| {
+                invalid(format!(
+                    "Unknown command '{name}' bound to '{chord}' in keybindings"
+                ))
+            })?;
+            self.bind(keys, command);
+        }
+
+        Ok(())
+    }
+
+    // The shortest chord bound to `command`, so the palette can show e.g.
+    // "Ctrl-S" rather than a longer alias such as "Ctrl-X Ctrl-S".
+    pub fn binding_for(&self, command: Command) -> Option<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == command)
+            .map(|(chord, _)| format_chord(chord))
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        if let Some(command) = self.bindings.get(keys) {
+            return Lookup::Command(*command);
+        }
+        if self
+            .bindings
+            .keys()
+            .any(|chord| chord.len() > keys.len() && chord.starts_with(keys))
+        {
+            return Lookup::Prefix;
+        }
+
+        Lookup::Unbound
+    }
+}
+
+// Whether `key` starts one of the chords vim mode leaves to the keymap.
+pub fn starts_vim_chord(key: Key) -> bool {
+    VIM_BINDINGS
+        .iter()
+        .filter_map(|(chord, _)| parse_chord(chord).ok())
+        .any(|keys| keys[0] == key)
+}
+
+pub fn parse_chord(chord: &str) -> Result<Vec<Key>, Error> {
+    let keys = chord
+        .split_whitespace()
+        .map(|key| {
+            parse_key(key).ok_or_else(|| invalid(format!("Invalid key '{key}' in chord '{chord}'")))
+        })
+        .collect::<Result<Vec<Key>, Error>>()?;
+    if keys.is_empty() {
+        return Err(invalid(String::from("Empty key chord in keybindings")));
+    }
+
+    Ok(keys)
+}
+
+fn parse_key(key: &str) -> Option<Key> {
+    if let Some(rest) = strip_modifier(key, &["ctrl-", "c-"]) {
+        return parse_ctrl(rest);
+    }
+    if let Some(rest) = strip_modifier(key, &["alt-", "m-"]) {
+        return match rest.to_lowercase().as_str() {
+            "left" => Some(Key::AltLeft),
+            "right" => Some(Key::AltRight),
+            "up" => Some(Key::AltUp),
+            "down" => Some(Key::AltDown),
+            _ => single_char(rest).map(Key::Alt),
+        };
+    }
+    if let Some(rest) = strip_modifier(key, &["shift-", "s-"]) {
+        return match rest.to_lowercase().as_str() {
+            "left" => Some(Key::ShiftLeft),
+            "right" => Some(Key::ShiftRight),
+            "up" => Some(Key::ShiftUp),
+            "down" => Some(Key::ShiftDown),
+            "tab" => Some(Key::BackTab),
+            _ => None,
+        };
+    }
+
+    let named = match key.to_lowercase().as_str() {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "esc" => Key::Esc,
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "backtab" => Key::BackTab,
+        "space" => Key::Char(' '),
+        lower => {
+            if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                return (1..=12).contains(&number).then_some(Key::F(number));
+            }
+            return single_char(key).map(Key::Char);
+        }
+    };
+
+    Some(named)
+}
+
+fn strip_modifier<'k>(key: &'k str, prefixes: &[&str]) -> Option<&'k str> {
+    prefixes.iter().find_map(|prefix| {
+        let head = key.get(..prefix.len())?;
+        let rest = &key[prefix.len()..];
+        (head.eq_ignore_ascii_case(prefix) && !rest.is_empty()).then_some(rest)
+    })
+}
+
+// The terminal cannot tell some control characters apart from their plain
+// keys, so map those chords onto the key termion will actually report.
+fn parse_ctrl(key: &str) -> Option<Key> {
+    match key.to_lowercase().as_str() {
+        "space" => return Some(Key::Null),
+        "left" => return Some(Key::CtrlLeft),
+        "right" => return Some(Key::CtrlRight),
+        "up" => return Some(Key::CtrlUp),
+        "down" => return Some(Key::CtrlDown),
+        "home" => return Some(Key::CtrlHome),
+        "end" => return Some(Key::CtrlEnd),
+        _ => (),
+    }
+    match single_char(key)?.to_ascii_lowercase() {
+        'i' => Some(Key::Char('\t')),
+        'j' | 'm' => Some(Key::Char('\n')),
+        c @ ('a'..='z' | '4'..='7') => Some(Key::Ctrl(c)),
+        _ => None,
+    }
+}
+
+fn single_char(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+pub fn format_chord(keys: &[Key]) -> String {
+    keys.iter()
+        .copied()
+        .map(format_key)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn format_key(key: Key) -> String {
+    match key {
+        Key::Ctrl(c) => format!("Ctrl-{}", c.to_ascii_uppercase()),
+        Key::Alt(c) => format!("Alt-{c}"),
+        Key::Null => String::from("Ctrl-Space"),
+        Key::Char('\n') => String::from("Enter"),
+        Key::Char('\t') => String::from("Tab"),
+        Key::Char(' ') => String::from("Space"),
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{n}"),
+        Key::PageUp => String::from("PageUp"),
+        Key::PageDown => String::from("PageDown"),
+        Key::CtrlLeft => String::from("Ctrl-Left"),
+        Key::CtrlRight => String::from("Ctrl-Right"),
+        Key::CtrlUp => String::from("Ctrl-Up"),
+        Key::CtrlDown => String::from("Ctrl-Down"),
+        Key::CtrlHome => String::from("Ctrl-Home"),
+        Key::CtrlEnd => String::from("Ctrl-End"),
+        Key::AltLeft => String::from("Alt-Left"),
+        Key::AltRight => String::from("Alt-Right"),
+        Key::AltUp => String::from("Alt-Up"),
+        Key::AltDown => String::from("Alt-Down"),
+        Key::ShiftLeft => String::from("Shift-Left"),
+        Key::ShiftRight => String::from("Shift-Right"),
+        Key::ShiftUp => String::from("Shift-Up"),
+        Key::ShiftDown => String::from("Shift-Down"),
+        Key::BackTab => String::from("Shift-Tab"),
+        other => format!("{other:?}"),
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
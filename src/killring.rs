@@ -0,0 +1,46 @@
+use std::collections::VecDeque;
+
+const CAPACITY: usize = 60;
+
+#[derive(Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    // Index of the entry the next yank inserts; yank-pop moves it backwards
+    // through older kills.
+    yank_index: usize,
+}
+
+impl KillRing {
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push_front(text);
+        self.entries.truncate(CAPACITY);
+        self.yank_index = 0;
+    }
+
+    // Joins a kill onto the most recent entry, so that consecutive kills can
+    // be yanked back in one go. `before` is set for kills that deleted text
+    // behind the cursor.
+    pub fn append(&mut self, text: &str, before: bool) {
+        match self.entries.front_mut() {
+            Some(entry) if before => entry.insert_str(0, text),
+            Some(entry) => entry.push_str(text),
+            None => self.push(text.to_string()),
+        }
+        self.yank_index = 0;
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.current()
+    }
+}
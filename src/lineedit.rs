@@ -0,0 +1,139 @@
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+
+const HISTORY_CAPACITY: usize = 100;
+
+// A single line of editable text with a cursor, as used by prompts. The
+// cursor is a grapheme index, like the editor's own.
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.len();
+    }
+
+    // Applies an editing or movement key, returning false for keys that are
+    // left to the caller.
+    pub fn handle(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char(c) if !c.is_control() => self.insert(c),
+            Key::Left | Key::Ctrl('b') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right | Key::Ctrl('f') => self.cursor = (self.cursor + 1).min(self.len()),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.len(),
+            Key::CtrlLeft | Key::Alt('b') => self.cursor = self.word_start(),
+            Key::CtrlRight | Key::Alt('f') => self.cursor = self.word_end(),
+            Key::Backspace | Key::Ctrl('h') if self.cursor > 0 => {
+                self.delete(self.cursor - 1, self.cursor);
+            }
+            Key::Delete | Key::Ctrl('d') => self.delete(self.cursor, self.cursor + 1),
+            Key::Ctrl('w') | Key::Alt('\x7f') => self.delete(self.word_start(), self.cursor),
+            Key::Alt('d') => self.delete(self.cursor, self.word_end()),
+            Key::Ctrl('u') => self.delete(0, self.cursor),
+            Key::Ctrl('k') => self.delete(self.cursor, self.len()),
+            Key::Backspace => (),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    fn byte_offset(&self, index: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(index)
+            .map_or(self.text.len(), |(offset, _)| offset)
+    }
+
+    fn insert(&mut self, c: char) {
+        let offset = self.byte_offset(self.cursor);
+        self.text.insert(offset, c);
+        self.cursor += 1;
+    }
+
+    fn delete(&mut self, start: usize, end: usize) {
+        let end = end.min(self.len());
+        if start >= end {
+            return;
+        }
+        let range = self.byte_offset(start)..self.byte_offset(end);
+        self.text.replace_range(range, "");
+        self.cursor = start;
+    }
+
+    // Word boundaries treat anything but letters and digits as separators,
+    // so that Ctrl-W in a path prompt deletes one path component at a time.
+    fn is_word(grapheme: &str) -> bool {
+        grapheme.chars().any(char::is_alphanumeric)
+    }
+
+    fn word_start(&self) -> usize {
+        let graphemes: Vec<&str> = self.text.graphemes(true).collect();
+        let mut index = self.cursor;
+        while index > 0 && !Self::is_word(graphemes[index - 1]) {
+            index -= 1;
+        }
+        while index > 0 && Self::is_word(graphemes[index - 1]) {
+            index -= 1;
+        }
+
+        index
+    }
+
+    fn word_end(&self) -> usize {
+        let graphemes: Vec<&str> = self.text.graphemes(true).collect();
+        let mut index = self.cursor;
+        while index < graphemes.len() && !Self::is_word(graphemes[index]) {
+            index += 1;
+        }
+        while index < graphemes.len() && Self::is_word(graphemes[index]) {
+            index += 1;
+        }
+
+        index
+    }
+}
+
+// Previous answers to one kind of prompt, oldest first.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn push(&mut self, entry: &str) {
+        if entry.is_empty() {
+            return;
+        }
+        self.entries.retain(|existing| existing != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > HISTORY_CAPACITY {
+            self.entries.remove(0);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+}
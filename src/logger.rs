@@ -1,4 +1,5 @@
-use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
 
 use chrono::prelude::*;
 
@@ -21,19 +22,23 @@ impl Logger {
     pub fn error(&self, msg: &str) { self.log("ERROR", msg);}
 
     fn log(&self, log_level: &str, msg: &str) {
-        let formatted_msg = &self.format_log_message(log_level, msg);
+        let formatted_msg = &Self::format_log_message(log_level, msg);
         self.write_to_log_file(formatted_msg);
     }
 
-    fn format_log_message(&self, log_level: &str, msg: &str) -> String {
+    fn format_log_message(log_level: &str, msg: &str) -> String {
         let time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        format!("[{}]: [{}]: {}\n", time, log_level, msg)
+        format!("[{time}]: [{log_level}]: {msg}\n")
     }
 
+    // Logging must never take the editor down, so write errors are ignored.
     fn write_to_log_file(&self, msg: &str) {
-        let mut existing_content = fs::read_to_string(&self.log_filepath).expect("Failed to read log file");
-        existing_content.push_str(msg);
-
-        fs::write(&self.log_filepath, existing_content).expect("Failed to write to log file");
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_filepath);
+        if let Ok(mut file) = file {
+            let _ = file.write_all(msg.as_bytes());
+        }
     }
 }
@@ -0,0 +1,807 @@
+use crate::config::LanguageServerConfig;
+use crate::document::RowChange;
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+
+// How long a server may take to answer `initialize` before it is given up
+// on.
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a server is given to answer `shutdown` and then exit when the
+// editor closes, before it is killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub enum Message {
+    Response {
+        id: u64,
+        result: Result<Value, String>,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+// A position as the protocol counts it: a line, and a column in UTF-16 code
+// units.
+#[derive(Clone, Copy)]
+pub struct LspPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+pub struct Diagnostic {
+    pub start: LspPosition,
+    pub end: LspPosition,
+    pub severity: Severity,
+    pub message: String,
+}
+
+pub struct Location {
+    pub path: PathBuf,
+    pub position: LspPosition,
+}
+
+pub struct CompletionItem {
+    pub text: String,
+    pub detail: String,
+}
+
+// A language server running as a child process, spoken to over its stdin
+// and stdout. Its messages are read on a background thread. Until it has
+// answered `initialize`, what is sent to it waits in `queued`.
+pub struct LanguageServer {
+    child: Option<Child>,
+    stdin: Box<dyn Write + Send>,
+    messages: Receiver<Value>,
+    next_id: u64,
+    // The id of the `initialize` request while it is unanswered, and when it
+    // was sent.
+    initializing: Option<(u64, Instant)>,
+    queued: Vec<Value>,
+    // The version last sent for each open document.
+    versions: HashMap<String, i64>,
+    // Whether the server wants the whole text on every change.
+    full_sync: bool,
+    // Whether the server answers folding range requests.
+    folding_ranges: bool,
+}
+
+impl LanguageServer {
+    pub fn start(config: &LanguageServerConfig, root: &Path) -> io::Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| closed("stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| closed("stdout"))?;
+        let mut server = Self::connect(stdin, stdout, root)?;
+        server.child = Some(child);
+
+        Ok(server)
+    }
+
+    // Talks to a server over the given streams, starting with `initialize`.
+    pub fn connect(
+        input: impl Write + Send + 'static,
+        output: impl Read + Send + 'static,
+        root: &Path,
+    ) -> io::Result<Self> {
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(output);
+            while let Some(message) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut server = Self {
+            child: None,
+            stdin: Box::new(input),
+            messages,
+            next_id: 0,
+            initializing: None,
+            queued: Vec::new(),
+            versions: HashMap::new(),
+            full_sync: false,
+            folding_ranges: false,
+        };
+        server.initialize(root)?;
+
+        Ok(server)
+    }
+
+    fn initialize(&mut self, root: &Path) -> io::Result<()> {
+        let root_uri = path_to_uri(root);
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "workspaceFolders": [{ "uri": root_uri, "name": root.display().to_string() }],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": { "relatedInformation": false },
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": { "linkSupport": true },
+                    "references": {},
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "foldingRange": { "lineFoldingOnly": true },
+                },
+                "general": { "positionEncodings": ["utf-16"] },
+            },
+        });
+        let id = self.next_id;
+        self.next_id += 1;
+        let message =
+            json!({ "jsonrpc": "2.0", "id": id, "method": "initialize", "params": params });
+        self.write(&message)?;
+        self.initializing = Some((id, Instant::now()));
+
+        Ok(())
+    }
+
+    // Takes in the answer to `initialize`, then sends what waited for it.
+    fn initialized(&mut self, capabilities: &Value) -> io::Result<()> {
+        let sync = &capabilities["capabilities"]["textDocumentSync"];
+        let kind = sync.get("change").unwrap_or(sync);
+        self.full_sync = kind.as_u64() == Some(1);
+        let folding = &capabilities["capabilities"]["foldingRangeProvider"];
+        self.folding_ranges = !folding.is_null() && folding != false;
+        self.initializing = None;
+
+        self.notify("initialized", &json!({}))?;
+        for message in std::mem::take(&mut self.queued) {
+            self.write(&message)?;
+        }
+        Ok(())
+    }
+
+    // Whether the server has answered `initialize`. Documents are only
+    // opened once it has, as how they are synced depends on the answer.
+    pub fn is_ready(&self) -> bool {
+        self.initializing.is_none()
+    }
+
+    fn send(&mut self, message: &Value) -> io::Result<()> {
+        if self.is_ready() {
+            self.write(message)
+        } else {
+            self.queued.push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn write(&mut self, message: &Value) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.stdin.flush()
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    // Sends a request, returning the id its response will carry.
+    pub fn request(&mut self, method: &str, params: &Value) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        Ok(id)
+    }
+
+    // The messages received since the last call, and whether the server is
+    // still running. Requests from the server are answered here, and so is
+    // `initialize`; a server that fails it or takes too long counts as
+    // stopped.
+    pub fn poll(&mut self) -> (Vec<Message>, bool) {
+        let mut received = Vec::new();
+        loop {
+            let message = match self.messages.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return (received, false),
+            };
+            match (self.handle(&message), self.initializing) {
+                (Some(Message::Response { id, result }), Some((initialize, _)))
+                    if id == initialize =>
+                {
+                    let started = result.and_then(|capabilities| {
+                        self.initialized(&capabilities)
+                            .map_err(|err| err.to_string())
+                    });
+                    if started.is_err() {
+                        return (received, false);
+                    }
+                }
+                (message, _) => received.extend(message),
+            }
+        }
+        let timed_out = self
+            .initializing
+            .is_some_and(|(_, sent)| sent.elapsed() > INITIALIZE_TIMEOUT);
+        (received, !timed_out)
+    }
+
+    fn handle(&mut self, message: &Value) -> Option<Message> {
+        let method = message["method"].as_str().map(String::from);
+        match (message.get("id"), method) {
+            (Some(id), Some(method)) => {
+                // Nothing is configurable from the server's side, so every
+                // request gets an empty answer.
+                let result = match method.as_str() {
+                    "workspace/configuration" => {
+                        let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                        Value::Array(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                let _ = self.send(&response);
+                None
+            }
+            (Some(id), None) => {
+                let result = match message.get("error") {
+                    Some(error) => Err(error["message"].as_str().unwrap_or("Error").to_string()),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                Some(Message::Response {
+                    id: id.as_u64()?,
+                    result,
+                })
+            }
+            (None, Some(method)) => Some(Message::Notification {
+                method,
+                params: message.get("params").cloned().unwrap_or(Value::Null),
+            }),
+            (None, None) => None,
+        }
+    }
+
+    pub fn provides_folding_ranges(&self) -> bool {
+        self.folding_ranges
+    }
+
+    pub fn is_open(&self, uri: &str) -> bool {
+        self.versions.contains_key(uri)
+    }
+
+    pub fn open_documents(&self) -> Vec<String> {
+        self.versions.keys().cloned().collect()
+    }
+
+    pub fn did_open(&mut self, uri: &str, language: &str, text: &str) -> io::Result<()> {
+        self.versions.insert(uri.to_string(), 0);
+        let document = json!({ "uri": uri, "languageId": language, "version": 0, "text": text });
+        self.notify("textDocument/didOpen", &json!({ "textDocument": document }))
+    }
+
+    // Tells the server about the rows that changed, or the whole of `text`
+    // for servers that only accept that.
+    pub fn did_change(&mut self, uri: &str, changes: &[RowChange], text: &str) -> io::Result<()> {
+        let version = self.versions.entry(uri.to_string()).or_insert(0);
+        *version += 1;
+        let version = *version;
+        let content_changes: Vec<Value> = if self.full_sync {
+            vec![json!({ "text": text })]
+        } else {
+            changes
+                .iter()
+                .map(|change| {
+                    let mut inserted = String::new();
+                    for line in &change.inserted {
+                        let _ = writeln!(inserted, "{line}");
+                    }
+                    json!({
+                        "range": {
+                            "start": { "line": change.at, "character": 0 },
+                            "end": { "line": change.at + change.removed, "character": 0 },
+                        },
+                        "text": inserted,
+                    })
+                })
+                .collect()
+        };
+        let params = json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": content_changes,
+        });
+        self.notify("textDocument/didChange", &params)
+    }
+
+    pub fn did_close(&mut self, uri: &str) -> io::Result<()> {
+        self.versions.remove(uri);
+        self.notify(
+            "textDocument/didClose",
+            &json!({ "textDocument": { "uri": uri } }),
+        )
+    }
+
+    pub fn did_save(&mut self, uri: &str) -> io::Result<()> {
+        self.notify(
+            "textDocument/didSave",
+            &json!({ "textDocument": { "uri": uri } }),
+        )
+    }
+}
+
+impl Drop for LanguageServer {
+    fn drop(&mut self) {
+        // A server still starting up has not been told anything worth
+        // shutting down cleanly for.
+        let mut deadline = Instant::now();
+        if self.is_ready() {
+            deadline += SHUTDOWN_TIMEOUT;
+            if let Ok(id) = self.request("shutdown", &Value::Null) {
+                while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                    match self.messages.recv_timeout(left) {
+                        Ok(message) if message.get("method").is_none() && message["id"] == id => {
+                            break
+                        }
+                        Ok(_) => {}
+                        Err(_) => break,
+                    }
+                }
+            }
+            let _ = self.notify("exit", &Value::Null);
+        }
+        if let Some(child) = self.child.as_mut() {
+            while Instant::now() < deadline && matches!(child.try_wait(), Ok(None)) {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Reads one message, framed by a Content-Length header.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+
+    // A malformed message is skipped rather than ending the conversation.
+    Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn closed(stream: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        format!("The language server closed its {stream}"),
+    )
+}
+
+pub fn text_document_position(path: &Path, position: LspPosition) -> Value {
+    json!({
+        "textDocument": { "uri": path_to_uri(path) },
+        "position": { "line": position.line, "character": position.character },
+    })
+}
+
+// The uri and diagnostics of a `textDocument/publishDiagnostics`.
+pub fn parse_diagnostics(params: &Value) -> Option<(String, Vec<Diagnostic>)> {
+    let uri = params["uri"].as_str()?.to_string();
+    let diagnostics = params["diagnostics"]
+        .as_array()?
+        .iter()
+        .filter_map(|diagnostic| {
+            let severity = match diagnostic["severity"].as_u64() {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            };
+            Some(Diagnostic {
+                start: parse_position(&diagnostic["range"]["start"])?,
+                end: parse_position(&diagnostic["range"]["end"])?,
+                severity,
+                message: diagnostic["message"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+
+    Some((uri, diagnostics))
+}
+
+// The locations in a definition or references response, which may be a
+// single location, a list of them, or a list of location links.
+pub fn parse_locations(result: &Value) -> Vec<Location> {
+    let items = match result {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        item => vec![item],
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let uri = item
+                .get("targetUri")
+                .or_else(|| item.get("uri"))?
+                .as_str()?;
+            let range = item
+                .get("targetSelectionRange")
+                .or_else(|| item.get("range"))?;
+            Some(Location {
+                path: uri_to_path(uri)?,
+                position: parse_position(&range["start"])?,
+            })
+        })
+        .collect()
+}
+
+// The text of a hover response, one entry per line, without the fences
+// around markdown code blocks.
+pub fn hover_lines(result: &Value) -> Vec<String> {
+    fn text(contents: &Value) -> String {
+        match contents {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts.iter().map(text).collect::<Vec<_>>().join("\n"),
+            contents => contents["value"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+    text(&result["contents"])
+        .lines()
+        .filter(|line| !line.starts_with("```"))
+        .map(String::from)
+        .collect()
+}
+
+// The items of a completion response, which is either a list of them or an
+// object holding one. Snippets are inserted as their plain label.
+pub fn completion_items(result: &Value) -> Vec<CompletionItem> {
+    let items = result
+        .as_array()
+        .or_else(|| result["items"].as_array())
+        .map_or(&[][..], Vec::as_slice);
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item["label"].as_str()?;
+            let snippet = item["insertTextFormat"].as_u64() == Some(2);
+            let text = item["textEdit"]["newText"]
+                .as_str()
+                .or_else(|| item["insertText"].as_str())
+                .filter(|_| !snippet)
+                .unwrap_or(label);
+            Some(CompletionItem {
+                text: text.to_string(),
+                detail: item["detail"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+// The first and last lines of the ranges in a folding range response.
+pub fn folding_ranges(result: &Value) -> Vec<(usize, usize)> {
+    let items = result.as_array().map_or(&[][..], Vec::as_slice);
+    items
+        .iter()
+        .filter_map(|item| {
+            let first = usize::try_from(item["startLine"].as_u64()?).ok()?;
+            let last = usize::try_from(item["endLine"].as_u64()?).ok()?;
+            Some((first, last))
+        })
+        .collect()
+}
+
+fn parse_position(position: &Value) -> Option<LspPosition> {
+    Some(LspPosition {
+        line: usize::try_from(position["line"].as_u64()?).ok()?,
+        character: usize::try_from(position["character"].as_u64()?).ok()?,
+    })
+}
+
+// The column, in UTF-16 code units, of grapheme `x` in `line`.
+pub fn utf16_column(line: &str, x: usize) -> usize {
+    line.graphemes(true)
+        .take(x)
+        .map(|grapheme| grapheme.encode_utf16().count())
+        .sum()
+}
+
+// The grapheme at UTF-16 column `character` of `line`.
+pub fn grapheme_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (x, grapheme) in line.graphemes(true).enumerate() {
+        if units >= character {
+            return x;
+        }
+        units += grapheme.encode_utf16().count();
+    }
+
+    line.graphemes(true).count()
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            let _ = write!(uri, "%{byte:02X}");
+        }
+    }
+
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let hex = encoded
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (encoded[index], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{PipeReader, PipeWriter};
+
+    // The server's end of the pipes a `LanguageServer` talks over.
+    struct MockServer {
+        received: BufReader<PipeReader>,
+        replies: PipeWriter,
+    }
+
+    impl MockServer {
+        fn receive(&mut self) -> Value {
+            read_message(&mut self.received).expect("the client closed its end")
+        }
+
+        fn send(&mut self, message: &Value) {
+            let body = message.to_string();
+            write!(self.replies, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        }
+
+        // Answers `initialize` with `capabilities`.
+        fn initialize(&mut self, capabilities: &Value) {
+            let request = self.receive();
+            assert_eq!(request["method"], "initialize");
+            self.send(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "capabilities": capabilities },
+            }));
+        }
+    }
+
+    fn connect() -> (LanguageServer, MockServer) {
+        let (received, input) = io::pipe().unwrap();
+        let (output, replies) = io::pipe().unwrap();
+        let server = LanguageServer::connect(input, output, Path::new("/project")).unwrap();
+        let mock = MockServer {
+            received: BufReader::new(received),
+            replies,
+        };
+        (server, mock)
+    }
+
+    // Polls until `done` says so, failing after a second.
+    fn poll_until(
+        server: &mut LanguageServer,
+        done: impl Fn(&LanguageServer, &[Message]) -> bool,
+    ) -> Vec<Message> {
+        let start = Instant::now();
+        loop {
+            let (messages, running) = server.poll();
+            assert!(running, "the server stopped");
+            if done(server, &messages) {
+                return messages;
+            }
+            assert!(start.elapsed() < Duration::from_secs(1), "nothing arrived");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn documents_wait_for_initialize() {
+        let (mut server, mut mock) = connect();
+        server
+            .did_open("file:///project/a.rs", "rust", "fn main() {}\n")
+            .unwrap();
+        assert!(!server.is_ready());
+
+        mock.initialize(&json!({ "textDocumentSync": 2 }));
+        poll_until(&mut server, |server, _| server.is_ready());
+        assert_eq!(mock.receive()["method"], "initialized");
+        let open = mock.receive();
+        assert_eq!(open["method"], "textDocument/didOpen");
+        assert_eq!(open["params"]["textDocument"]["text"], "fn main() {}\n");
+    }
+
+    #[test]
+    fn changes_are_sent_by_row() {
+        let (mut server, mut mock) = connect();
+        mock.initialize(&json!({ "textDocumentSync": { "change": 2 } }));
+        poll_until(&mut server, |server, _| server.is_ready());
+        mock.receive();
+
+        let uri = "file:///project/a.rs";
+        server.did_open(uri, "rust", "a\nb\nc\n").unwrap();
+        let change = RowChange {
+            at: 1,
+            removed: 1,
+            inserted: vec!["x".to_string(), "y".to_string()],
+        };
+        server.did_change(uri, &[change], "a\nx\ny\nc\n").unwrap();
+
+        assert_eq!(mock.receive()["method"], "textDocument/didOpen");
+        let change = mock.receive();
+        assert_eq!(change["method"], "textDocument/didChange");
+        assert_eq!(change["params"]["textDocument"]["version"], 1);
+        assert_eq!(
+            change["params"]["contentChanges"],
+            json!([{
+                "range": {
+                    "start": { "line": 1, "character": 0 },
+                    "end": { "line": 2, "character": 0 },
+                },
+                "text": "x\ny\n",
+            }])
+        );
+    }
+
+    #[test]
+    fn whole_text_is_sent_when_the_server_asks_for_it() {
+        let (mut server, mut mock) = connect();
+        mock.initialize(&json!({ "textDocumentSync": 1 }));
+        poll_until(&mut server, |server, _| server.is_ready());
+        mock.receive();
+
+        let uri = "file:///project/a.rs";
+        server.did_open(uri, "rust", "a\n").unwrap();
+        server.did_change(uri, &[], "b\n").unwrap();
+
+        mock.receive();
+        let change = mock.receive();
+        assert_eq!(
+            change["params"]["contentChanges"],
+            json!([{ "text": "b\n" }])
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_received() {
+        let (mut server, mut mock) = connect();
+        mock.initialize(&json!({}));
+        poll_until(&mut server, |server, _| server.is_ready());
+
+        mock.send(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": "file:///project/a.rs",
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 2, "character": 4 },
+                        "end": { "line": 2, "character": 7 },
+                    },
+                    "severity": 2,
+                    "message": "unused variable",
+                }],
+            },
+        }));
+        let messages = poll_until(&mut server, |_, messages| !messages.is_empty());
+
+        let Some(Message::Notification { method, params }) = messages.first() else {
+            panic!("expected a notification");
+        };
+        assert_eq!(method, "textDocument/publishDiagnostics");
+        let (uri, diagnostics) = parse_diagnostics(params).unwrap();
+        assert_eq!(uri, "file:///project/a.rs");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            (diagnostics[0].start.line, diagnostics[0].start.character),
+            (2, 4)
+        );
+        assert!(matches!(diagnostics[0].severity, Severity::Warning));
+        assert_eq!(diagnostics[0].message, "unused variable");
+    }
+
+    #[test]
+    fn server_requests_are_answered() {
+        let (mut server, mut mock) = connect();
+        mock.initialize(&json!({}));
+        poll_until(&mut server, |server, _| server.is_ready());
+        mock.receive();
+
+        mock.send(&json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "workspace/configuration",
+            "params": { "items": [{}, {}] },
+        }));
+        // The request is answered by the time what follows it arrives.
+        mock.send(&json!({ "jsonrpc": "2.0", "method": "window/logMessage", "params": {} }));
+        poll_until(&mut server, |_, messages| !messages.is_empty());
+        let response = mock.receive();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"], json!([null, null]));
+    }
+
+    #[test]
+    fn a_failed_initialize_stops_the_server() {
+        let (mut server, mut mock) = connect();
+        let request = mock.receive();
+        mock.send(&json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "code": -32603, "message": "no" },
+        }));
+        let start = Instant::now();
+        while server.poll().1 {
+            assert!(
+                start.elapsed() < Duration::from_secs(1),
+                "the server kept running"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
@@ -1,24 +1,131 @@
 #![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc, clippy::must_use_candidate)]
 mod editor;
 mod terminal;
 mod document;
 mod row;
 mod logger;
 mod config;
+mod command;
+mod keymap;
+mod fuzzy;
+mod palette;
+mod motion;
+mod vim;
+mod killring;
+mod goto;
+mod jumplist;
+mod args;
+mod lineedit;
+mod completion;
+mod explorer;
+mod undo;
+mod project;
+mod finder;
+mod grep;
+mod replace;
+mod lsp;
+mod autocomplete;
+mod build;
+mod pipe;
+mod diff;
+mod git;
+mod conflict;
+mod fold;
 
-use editor::Editor;
+use args::Invocation;
+use std::io::Write;
+use editor::{Buffer, Editor};
 pub use editor::Position;
 pub use terminal::Terminal;
 pub use document::Document;
 pub use row::Row;
 pub use logger::Logger;
+pub use command::Command;
+pub use keymap::Keymap;
 use crate::config::Config;
 
 fn main() {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(Invocation::Edit(args)) => args,
+        Ok(Invocation::Help) => {
+            println!("{}", args::USAGE);
+            return;
+        }
+        Ok(Invocation::Version) => {
+            println!("bolt {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(err) => {
+            eprintln!("bolt: {err}\nTry 'bolt --help' for more information.");
+            std::process::exit(2);
+        }
+    };
+
     // TODO - Use default config if load fails
-    let config = Config::load("config.json").expect("Failed to load config");
-    let logger = Logger::new("log.txt");
+    let config = match Config::load(&args.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Failed to load config: {err}");
+            std::process::exit(1);
+        }
+    };
 
-    Editor::new(config, &logger).run();
-}
+    // Files are read before the terminal enters raw mode so that errors can
+    // be reported on a normal screen.
+    let mut buffers = Vec::new();
+    for file in &args.files {
+        let document = if file.path == "-" {
+            std::io::read_to_string(std::io::stdin()).map(|text| Document::from_text(&text))
+        } else {
+            Document::open_or_new(&file.path)
+        };
+        let mut document = match document {
+            Ok(document) => document,
+            Err(err) => {
+                eprintln!("bolt: {}: {err}", file.path);
+                std::process::exit(1);
+            }
+        };
+        if args.readonly {
+            document.set_read_only(true);
+        }
+        let y = file.line.unwrap_or(0).min(document.len().saturating_sub(1));
+        let x = match file.column {
+            Some(column) => column.min(document.row(y).map_or(0, Row::len)),
+            None if file.line.is_some() => motion::first_non_blank(&document, y),
+            None => 0,
+        };
+        buffers.push(Buffer::new(document, Position { x, y }));
+    }
+    let original_stdout = match Terminal::detach_stdout() {
+        Ok(original_stdout) => original_stdout,
+        Err(err) => {
+            eprintln!("bolt: cannot open the terminal: {err}");
+            std::process::exit(1);
+        }
+    };
+    let logger = Logger::new(&args.log);
 
+    let output = {
+        let mut editor = Editor::new(config, &logger, buffers);
+        if args.stdout {
+            editor.send_to_stdout();
+        }
+        if args.diff {
+            editor.show_diff(0, 1);
+        }
+        editor.run();
+        editor.stdout_text()
+    };
+    if let Some(text) = output {
+        let written = match original_stdout {
+            Some(mut file) => file.write_all(text.as_bytes()),
+            None => std::io::stdout().write_all(text.as_bytes()),
+        };
+        if let Err(err) = written {
+            eprintln!("bolt: failed to write to stdout: {err}");
+            std::process::exit(1);
+        }
+    }
+}
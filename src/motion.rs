@@ -0,0 +1,449 @@
+use crate::{Document, Position};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Blank,
+    Newline,
+    Word,
+    Punctuation,
+}
+
+pub struct TextRange {
+    pub start: Position,
+    // Exclusive, unless `linewise` is set, in which case the range covers the
+    // whole of the rows `start.y..=end.y`.
+    pub end: Position,
+    pub linewise: bool,
+}
+
+fn class_of(grapheme: &str, big: bool) -> Class {
+    let c = grapheme.chars().next().unwrap_or(' ');
+    if c.is_whitespace() {
+        Class::Blank
+    } else if big || c.is_alphanumeric() || c == '_' {
+        Class::Word
+    } else {
+        Class::Punctuation
+    }
+}
+
+// The position one past the end of a row stands for its line break.
+fn class_at(document: &Document, at: Position, big: bool) -> Class {
+    document
+        .row(at.y)
+        .and_then(|row| row.grapheme(at.x))
+        .map_or(Class::Newline, |grapheme| class_of(grapheme, big))
+}
+
+fn row_len(document: &Document, y: usize) -> usize {
+    document.row(y).map_or(0, crate::Row::len)
+}
+
+fn next(document: &Document, at: Position) -> Option<Position> {
+    if at.y >= document.len() {
+        None
+    } else if at.x < row_len(document, at.y) {
+        Some(Position {
+            x: at.x + 1,
+            y: at.y,
+        })
+    } else if at.y + 1 < document.len() {
+        Some(Position { x: 0, y: at.y + 1 })
+    } else {
+        None
+    }
+}
+
+fn previous(document: &Document, at: Position) -> Option<Position> {
+    if at.x > 0 {
+        Some(Position {
+            x: at.x - 1,
+            y: at.y,
+        })
+    } else if at.y > 0 {
+        let y = at.y.min(document.len()) - 1;
+        Some(Position {
+            x: row_len(document, y),
+            y,
+        })
+    } else {
+        None
+    }
+}
+
+fn is_empty_line(document: &Document, y: usize) -> bool {
+    row_len(document, y) == 0
+}
+
+pub fn word_forward(document: &Document, from: Position, big: bool) -> Position {
+    let mut at = from;
+    let start_class = class_at(document, at, big);
+    if matches!(start_class, Class::Word | Class::Punctuation) {
+        while class_at(document, at, big) == start_class {
+            match next(document, at) {
+                Some(position) => at = position,
+                None => return at,
+            }
+        }
+    }
+    loop {
+        match class_at(document, at, big) {
+            Class::Newline if at != from && is_empty_line(document, at.y) => return at,
+            Class::Blank | Class::Newline => (),
+            _ => return at,
+        }
+        match next(document, at) {
+            Some(position) => at = position,
+            None => return at,
+        }
+    }
+}
+
+pub fn word_backward(document: &Document, from: Position, big: bool) -> Position {
+    let Some(mut at) = previous(document, from) else {
+        return from;
+    };
+    loop {
+        match class_at(document, at, big) {
+            Class::Newline if is_empty_line(document, at.y) => return at,
+            Class::Blank | Class::Newline => (),
+            _ => break,
+        }
+        match previous(document, at) {
+            Some(position) => at = position,
+            None => return at,
+        }
+    }
+    let class = class_at(document, at, big);
+    while let Some(position) = previous(document, at) {
+        if class_at(document, position, big) != class {
+            break;
+        }
+        at = position;
+    }
+
+    at
+}
+
+pub fn word_end(document: &Document, from: Position, big: bool) -> Position {
+    let Some(mut at) = next(document, from) else {
+        return from;
+    };
+    while matches!(class_at(document, at, big), Class::Blank | Class::Newline) {
+        match next(document, at) {
+            Some(position) => at = position,
+            None => return at,
+        }
+    }
+    let class = class_at(document, at, big);
+    while let Some(position) = next(document, at) {
+        if class_at(document, position, big) != class {
+            break;
+        }
+        at = position;
+    }
+
+    at
+}
+
+pub fn first_non_blank(document: &Document, y: usize) -> usize {
+    document.row(y).map_or(0, |row| {
+        row.graphemes()
+            .iter()
+            .position(|grapheme| class_of(grapheme, false) != Class::Blank)
+            .unwrap_or_else(|| row.len())
+    })
+}
+
+// Finds the `count`th occurrence of `target` on the line. With `till` the
+// result stops one short of the match, as vim's t and T motions do. A
+// repeated till skips a match right next to the cursor, which it would
+// otherwise stop before again.
+pub fn find_in_line(
+    document: &Document,
+    from: Position,
+    target: &str,
+    forward: bool,
+    till: bool,
+    repeat: bool,
+    count: usize,
+) -> Option<Position> {
+    let graphemes = document.row(from.y)?.graphemes();
+    let mut x = from.x;
+    if till && repeat {
+        x = if forward { x + 1 } else { x.checked_sub(1)? };
+    }
+    for _ in 0..count {
+        x = if forward {
+            (x + 1..graphemes.len()).find(|&i| graphemes[i] == target)?
+        } else {
+            (0..x).rev().find(|&i| graphemes[i] == target)?
+        };
+    }
+    if till {
+        x = if forward { x - 1 } else { x + 1 };
+    }
+
+    Some(Position { x, y: from.y })
+}
+
+fn is_blank_line(document: &Document, y: usize) -> bool {
+    document
+        .row(y)
+        .is_none_or(|row| row.as_str().trim().is_empty())
+}
+
+pub fn paragraph_forward(document: &Document, from: usize) -> usize {
+    let last = document.len().saturating_sub(1);
+    let mut y = from;
+    while y < last && is_blank_line(document, y) {
+        y += 1;
+    }
+    while y < last && !is_blank_line(document, y) {
+        y += 1;
+    }
+
+    y
+}
+
+pub fn paragraph_backward(document: &Document, from: usize) -> usize {
+    let mut y = from.min(document.len().saturating_sub(1));
+    while y > 0 && is_blank_line(document, y) {
+        y -= 1;
+    }
+    while y > 0 && !is_blank_line(document, y) {
+        y -= 1;
+    }
+
+    y
+}
+
+pub fn word_object(document: &Document, at: Position, inner: bool, big: bool) -> Option<TextRange> {
+    let row = document.row(at.y)?;
+    if row.is_empty() {
+        return None;
+    }
+    let graphemes = row.graphemes();
+    let x = at.x.min(graphemes.len() - 1);
+    let class = class_of(graphemes[x], big);
+    let same = |i: usize| class_of(graphemes[i], big) == class;
+
+    let mut start = x;
+    while start > 0 && same(start - 1) {
+        start -= 1;
+    }
+    let mut end = x + 1;
+    while end < graphemes.len() && same(end) {
+        end += 1;
+    }
+
+    if !inner {
+        let is_blank = |i: usize| class_of(graphemes[i], big) == Class::Blank;
+        if class == Class::Blank {
+            let word_class = graphemes.get(end).map(|grapheme| class_of(grapheme, big));
+            while end < graphemes.len() && Some(class_of(graphemes[end], big)) == word_class {
+                end += 1;
+            }
+        } else if end < graphemes.len() && is_blank(end) {
+            while end < graphemes.len() && is_blank(end) {
+                end += 1;
+            }
+        } else {
+            while start > 0 && is_blank(start - 1) {
+                start -= 1;
+            }
+        }
+    }
+
+    Some(TextRange {
+        start: Position { x: start, y: at.y },
+        end: Position { x: end, y: at.y },
+        linewise: false,
+    })
+}
+
+pub fn quote_object(
+    document: &Document,
+    at: Position,
+    quote: &str,
+    inner: bool,
+) -> Option<TextRange> {
+    let graphemes = document.row(at.y)?.graphemes();
+    let quotes: Vec<usize> = (0..graphemes.len())
+        .filter(|&i| graphemes[i] == quote && (i == 0 || graphemes[i - 1] != "\\"))
+        .collect();
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(_, close)| at.x <= close)?;
+
+    let (start, mut end) = if inner {
+        (open + 1, close)
+    } else {
+        (open, close + 1)
+    };
+    if !inner {
+        while end < graphemes.len() && class_of(graphemes[end], false) == Class::Blank {
+            end += 1;
+        }
+    }
+
+    Some(TextRange {
+        start: Position { x: start, y: at.y },
+        end: Position { x: end, y: at.y },
+        linewise: false,
+    })
+}
+
+pub fn bracket_object(
+    document: &Document,
+    at: Position,
+    open: &str,
+    close: &str,
+    inner: bool,
+) -> Option<TextRange> {
+    let grapheme_at = |position: Position| {
+        document
+            .row(position.y)
+            .and_then(|row| row.grapheme(position.x))
+    };
+
+    let mut start = at;
+    let mut depth = 0;
+    loop {
+        match grapheme_at(start) {
+            Some(grapheme) if grapheme == open && depth == 0 => break,
+            Some(grapheme) if grapheme == open => depth -= 1,
+            Some(grapheme) if grapheme == close && start != at => depth += 1,
+            _ => (),
+        }
+        start = previous(document, start)?;
+    }
+
+    let mut end = next(document, start)?;
+    let mut depth = 0;
+    loop {
+        match grapheme_at(end) {
+            Some(grapheme) if grapheme == close && depth == 0 => break,
+            Some(grapheme) if grapheme == close => depth -= 1,
+            Some(grapheme) if grapheme == open => depth += 1,
+            _ => (),
+        }
+        end = next(document, end)?;
+    }
+
+    if !inner {
+        return Some(TextRange {
+            start,
+            end: Position {
+                x: end.x + 1,
+                y: end.y,
+            },
+            linewise: false,
+        });
+    }
+
+    // A block whose braces sit on their own lines is worked on line by line,
+    // so that `di{` leaves the braces in place rather than joining them.
+    let open_ends_line = start.x + 1 == row_len(document, start.y);
+    let close_starts_line = first_non_blank(document, end.y) == end.x;
+    if open_ends_line && close_starts_line && end.y > start.y + 1 {
+        return Some(TextRange {
+            start: Position {
+                x: 0,
+                y: start.y + 1,
+            },
+            end: Position { x: 0, y: end.y - 1 },
+            linewise: true,
+        });
+    }
+
+    Some(TextRange {
+        start: next(document, start)?,
+        end,
+        linewise: false,
+    })
+}
+
+pub fn paragraph_object(document: &Document, y: usize, inner: bool) -> Option<TextRange> {
+    if y >= document.len() {
+        return None;
+    }
+    let blank = is_blank_line(document, y);
+    let mut start = y;
+    while start > 0 && is_blank_line(document, start - 1) == blank {
+        start -= 1;
+    }
+    let mut end = y;
+    while end + 1 < document.len() && is_blank_line(document, end + 1) == blank {
+        end += 1;
+    }
+    if !inner {
+        while end + 1 < document.len() && is_blank_line(document, end + 1) != blank {
+            end += 1;
+        }
+    }
+
+    Some(TextRange {
+        start: Position { x: 0, y: start },
+        end: Position { x: 0, y: end },
+        linewise: true,
+    })
+}
+
+// Grapheme spans of the words on a row, using Unicode word boundaries. A
+// segment counts as a word when it contains an alphanumeric character.
+fn word_spans(row: &crate::Row) -> Vec<(usize, usize)> {
+    let text = row.as_str();
+    let offsets: Vec<usize> = text.grapheme_indices(true).map(|(i, _)| i).collect();
+    let index_of = |byte: usize| offsets.partition_point(|&offset| offset < byte);
+
+    text.split_word_bound_indices()
+        .filter(|(_, segment)| segment.chars().any(char::is_alphanumeric))
+        .map(|(start, segment)| (index_of(start), index_of(start + segment.len())))
+        .collect()
+}
+
+// Moves to just past the end of the next word, as Emacs' forward-word does.
+pub fn next_word_end(document: &Document, from: Position) -> Position {
+    let mut at = from;
+    while let Some(row) = document.row(at.y) {
+        if let Some((_, end)) = word_spans(row).into_iter().find(|(_, end)| *end > at.x) {
+            return Position { x: end, y: at.y };
+        }
+        if at.y + 1 >= document.len() {
+            return Position {
+                x: row.len(),
+                y: at.y,
+            };
+        }
+        at = Position { x: 0, y: at.y + 1 };
+    }
+
+    at
+}
+
+// Moves to the start of the previous word, as Emacs' backward-word does.
+pub fn previous_word_start(document: &Document, from: Position) -> Position {
+    let mut at = from;
+    loop {
+        if let Some(row) = document.row(at.y) {
+            let start = word_spans(row)
+                .into_iter()
+                .rev()
+                .find(|(start, _)| *start < at.x);
+            if let Some((start, _)) = start {
+                return Position { x: start, y: at.y };
+            }
+        }
+        if at.y == 0 {
+            return Position { x: 0, y: 0 };
+        }
+        at = Position {
+            x: row_len(document, at.y - 1),
+            y: at.y - 1,
+        };
+    }
+}
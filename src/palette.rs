@@ -0,0 +1,90 @@
+use crate::fuzzy::fuzzy_match;
+use crate::{Command, Keymap};
+
+struct Entry {
+    command: Command,
+    binding: String,
+}
+
+pub struct Palette {
+    entries: Vec<Entry>,
+    matches: Vec<usize>,
+    selected: usize,
+    pub accepted: bool,
+}
+
+impl Palette {
+    pub fn new(keymap: &Keymap) -> Self {
+        let entries: Vec<Entry> = Command::all()
+            .filter(|command| *command != Command::CommandPalette)
+            .map(|command| Entry {
+                command,
+                binding: keymap.binding_for(command).unwrap_or_default(),
+            })
+            .collect();
+        let matches = (0..entries.len()).collect();
+
+        Self {
+            entries,
+            matches,
+            selected: 0,
+            accepted: false,
+        }
+    }
+
+    pub fn filter(&mut self, query: &str) {
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                fuzzy_match(query, entry.command.name()).map(|found| (found.score, index))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected_command(&self) -> Option<Command> {
+        self.matches
+            .get(self.selected)
+            .map(|index| self.entries[*index].command)
+    }
+
+    // Returns at most `height` formatted lines, scrolled so the selection is
+    // visible, along with the index of the selected line among them.
+    pub fn lines(&self, height: usize, width: usize) -> (Vec<String>, usize) {
+        let first = (self.selected + 1).saturating_sub(height);
+        let lines = self
+            .matches
+            .iter()
+            .skip(first)
+            .take(height)
+            .map(|index| {
+                let entry = &self.entries[*index];
+                format!(
+                    " {:<18} {:<16} {}",
+                    entry.command.name(),
+                    entry.binding,
+                    entry.command.description()
+                )
+                .chars()
+                .take(width)
+                .collect()
+            })
+            .collect();
+
+        (lines, self.selected - first)
+    }
+}
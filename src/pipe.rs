@@ -0,0 +1,112 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Output {
+    // The first lines of stderr joined into one, to fit in the message bar,
+    // or the exit status when the command printed nothing.
+    pub fn error_message(&self) -> String {
+        let stderr = self
+            .stderr
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if stderr.is_empty() {
+            self.status.to_string()
+        } else {
+            stderr
+        }
+    }
+}
+
+// Runs `program` in `dir` with `input` on its stdin, collecting what it
+// writes. A command still running after `timeout` is killed, and output
+// still open then, as when the command left a process running that holds
+// its pipes, is given up on along with the threads reading it.
+pub fn run(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    input: &str,
+    timeout: Duration,
+) -> io::Result<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed and drain the pipes on their own threads, so a command that
+    // writes before reading all of its input cannot block us.
+    let mut stdin = child.stdin.take();
+    let input = input.to_string();
+    thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let stdout = child.stdout.take().map(read_all);
+    let stderr = child.stderr.take().map(read_all);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(timed_out(timeout));
+        }
+        thread::sleep(WAIT_INTERVAL);
+    };
+
+    let collect = |output: Option<Receiver<Vec<u8>>>| {
+        let Some(output) = output else {
+            return Ok(String::new());
+        };
+        let bytes = match output.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(bytes) => bytes,
+            Err(RecvTimeoutError::Timeout) => return Err(timed_out(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Vec::new(),
+        };
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout)?,
+        stderr: collect(stderr)?,
+    })
+}
+
+fn read_all(mut output: impl Read + Send + 'static) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = output.read_to_end(&mut bytes);
+        let _ = sender.send(bytes);
+    });
+    receiver
+}
+
+fn timed_out(timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("timed out after {}s", timeout.as_secs()),
+    )
+}
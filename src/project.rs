@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// The nearest directory at or above `start` that holds a `.git`, or `start`
+// itself outside a repository.
+pub fn project_root(start: &Path) -> PathBuf {
+    start
+        .ancestors()
+        .find(|directory| directory.join(".git").exists())
+        .unwrap_or(start)
+        .to_path_buf()
+}
+
+// Where cargo runs the compiler, which the paths in its messages are
+// relative to: the nearest directory at or above `start` whose `Cargo.toml`
+// declares a workspace, or else the nearest one with a `Cargo.toml`.
+// Outside a cargo project it is the project root.
+pub fn workspace_root(start: &Path) -> PathBuf {
+    let packages: Vec<&Path> = start
+        .ancestors()
+        .filter(|directory| directory.join("Cargo.toml").is_file())
+        .collect();
+    let workspace = packages.iter().find(|directory| {
+        fs::read_to_string(directory.join("Cargo.toml"))
+            .is_ok_and(|manifest| manifest.lines().any(|line| line.trim() == "[workspace]"))
+    });
+    workspace
+        .or(packages.first())
+        .map_or_else(|| project_root(start), |directory| directory.to_path_buf())
+}
+
+// Lists the files under `root` that git would not ignore, on a background
+// thread. The walk stops early once the receiver is dropped.
+pub fn walk_files(root: &Path) -> Receiver<PathBuf> {
+    let (sender, receiver) = mpsc::channel();
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    thread::spawn(move || {
+        let files = walker
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()));
+        for entry in files {
+            if sender.send(entry.into_path()).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
@@ -0,0 +1,142 @@
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A run of adjacent lines that the replacement changes. Lines are matched
+// one at a time, as `:s` does, so a hunk always keeps its number of lines.
+pub struct Hunk {
+    pub start: usize,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+    pub included: bool,
+}
+
+pub struct FileChanges {
+    pub path: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileChanges {
+    pub fn included(&self) -> impl Iterator<Item = &Hunk> {
+        self.hunks.iter().filter(|hunk| hunk.included)
+    }
+}
+
+// The hunks replacing every match of `regex` in `text` would make.
+pub fn hunks(text: &str, regex: &Regex, replacement: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if !regex.is_match(line) {
+            continue;
+        }
+        let new = regex.replace_all(line, replacement).into_owned();
+        if new == line {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some(hunk) if hunk.start + hunk.old.len() == index => {
+                hunk.old.push(line.to_string());
+                hunk.new.push(new);
+            }
+            _ => hunks.push(Hunk {
+                start: index,
+                old: vec![line.to_string()],
+                new: vec![new],
+                included: true,
+            }),
+        }
+    }
+
+    hunks
+}
+
+// Applies the included hunks to `text`, keeping its line endings. Fails if
+// a hunk's lines no longer read as they did when it was made.
+pub fn apply(text: &str, hunks: &[&Hunk]) -> Result<String, String> {
+    let mut lines: Vec<String> = text.split_inclusive('\n').map(String::from).collect();
+    for hunk in hunks {
+        for (offset, (old, new)) in hunk.old.iter().zip(&hunk.new).enumerate() {
+            let index = hunk.start + offset;
+            let Some(line) = lines.get_mut(index) else {
+                return Err(format!("line {} is gone", index + 1));
+            };
+            let content = line.trim_end_matches('\n').trim_end_matches('\r');
+            if content != old {
+                return Err(format!("line {} has changed", index + 1));
+            }
+            let ending = line[content.len()..].to_string();
+            *line = format!("{new}{ending}");
+        }
+    }
+
+    Ok(lines.concat())
+}
+
+// Writes every file or none of them: all the new contents go to temporary
+// files first, which are then renamed over the originals. The originals are
+// kept aside until every rename is done, and put back if one fails.
+pub fn write_all(files: &[(PathBuf, String)]) -> io::Result<()> {
+    let mut temporaries: Vec<PathBuf> = Vec::new();
+    for (path, contents) in files {
+        let temporary = sibling_path(path, "tmp");
+        let written = fs::write(&temporary, contents);
+        temporaries.push(temporary);
+        if let Err(err) = written {
+            remove_all(&temporaries);
+            return Err(err);
+        }
+    }
+
+    // The files renamed so far, with where their original was kept.
+    let mut replaced: Vec<(&Path, Option<PathBuf>)> = Vec::new();
+    for ((path, _), temporary) in files.iter().zip(&temporaries) {
+        if let Err(err) = replace_file(path, temporary, &mut replaced) {
+            for (path, backup) in replaced.iter().rev() {
+                let _ = match backup {
+                    Some(backup) => fs::rename(backup, path),
+                    None => fs::remove_file(path),
+                };
+            }
+            remove_all(&temporaries);
+            return Err(err);
+        }
+    }
+    for backup in replaced.iter().filter_map(|(_, backup)| backup.as_ref()) {
+        let _ = fs::remove_file(backup);
+    }
+
+    Ok(())
+}
+
+// Moves `temporary` to `path`, keeping the file it replaces as a backup.
+fn replace_file<'a>(
+    path: &'a Path,
+    temporary: &Path,
+    replaced: &mut Vec<(&'a Path, Option<PathBuf>)>,
+) -> io::Result<()> {
+    let backup = match fs::metadata(path) {
+        Ok(metadata) => {
+            let _ = fs::set_permissions(temporary, metadata.permissions());
+            let backup = sibling_path(path, "bak");
+            fs::rename(path, &backup)?;
+            Some(backup)
+        }
+        Err(_) => None,
+    };
+    let renamed = fs::rename(temporary, path);
+    // Even when the rename failed, the original has to be put back.
+    replaced.push((path, backup));
+    renamed
+}
+
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.bolt-{suffix}"))
+}
@@ -1,7 +1,7 @@
 use std::cmp;
 use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Row {
     string: String,
     len: usize,
@@ -30,16 +30,21 @@ impl Row {
             .skip(start)
             .take(end - start)
         {
-            if grapheme == "\t" {
-                result.push_str(" ");
-            } else {
-                result.push_str(grapheme);
-            }
+            result.push_str(Self::render_grapheme(grapheme));
         }
 
         result
     }
 
+    // How one grapheme of the row is shown on screen.
+    pub fn render_grapheme(grapheme: &str) -> &str {
+        if grapheme == "\t" {
+            " "
+        } else {
+            grapheme
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -48,7 +53,7 @@ impl Row {
         self.len == 0
     }
     pub fn update_len(&mut self) {
-        self.len = self.string[..].graphemes(true).count()
+        self.len = self.string[..].graphemes(true).count();
     }
 
     pub fn insert(&mut self, at: usize, c: char) {
@@ -81,6 +86,7 @@ impl Row {
         self.update_len();
     }
 
+    #[must_use]
     pub fn split(&mut self, at: usize) -> Self {
         let beginning: String = self.string[..].graphemes(true).take(at).collect();
         let remainder: String = self.string[..].graphemes(true).skip(at).collect();
@@ -90,6 +96,43 @@ impl Row {
         Self::from(&remainder[..])
     }
 
+    pub fn insert_str(&mut self, at: usize, text: &str) {
+        let mut result: String = self.string[..].graphemes(true).take(at).collect();
+        let remainder: String = self.string[..].graphemes(true).skip(at).collect();
+        result.push_str(text);
+        result.push_str(&remainder);
+        self.string = result;
+        self.update_len();
+    }
+
+    pub fn delete_range(&mut self, start: usize, end: usize) {
+        let mut result: String = self.string[..].graphemes(true).take(start).collect();
+        let remainder: String = self.string[..].graphemes(true).skip(end).collect();
+        result.push_str(&remainder);
+        self.string = result;
+        self.update_len();
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        self.string[..]
+            .graphemes(true)
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect()
+    }
+
+    pub fn grapheme(&self, at: usize) -> Option<&str> {
+        self.string[..].graphemes(true).nth(at)
+    }
+
+    pub fn graphemes(&self) -> Vec<&str> {
+        self.string[..].graphemes(true).collect()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         self.string.as_bytes()
     }
@@ -1,6 +1,11 @@
 use crate::{Logger, Position};
 
-use std::io::{self, stdout, Write};
+use std::fs::File;
+use std::io::{self, stdout, IsTerminal, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 use termion::{
     color,
     event::Key,
@@ -15,24 +20,66 @@ pub struct Size {
 
 pub struct Terminal<'a> {
     size: Size,
-    logger: &'a Logger,
+    _logger: &'a Logger,
     _stdout: RawTerminal<std::io::Stdout>,
+    keys: Receiver<Result<Key, std::io::Error>>,
 }
 
 impl<'a> Terminal<'a> {
     pub fn new(logger: &'a Logger) -> Result<Self, std::io::Error> {
         let size = termion::terminal_size()?;
+        // Keys come from the terminal even when stdin is a pipe holding the
+        // document, as in `git log | bolt -`.
+        let input: Box<dyn Read + Send> = if io::stdin().is_terminal() {
+            Box::new(io::stdin())
+        } else {
+            Box::new(termion::get_tty()?)
+        };
+        // Keys are read on their own thread so that the editor can wait for
+        // one with a timeout while background work reports progress.
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for key in input.keys() {
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
 
         Ok(Self {
             size: Size {
                 width: size.0,
                 height: size.1.saturating_sub(1), // Leave room for status bars
             },
-            logger,
+            _logger: logger,
             _stdout: stdout().into_raw_mode()?,
+            keys,
         })
     }
 
+    // When stdout is not a terminal, points it at /dev/tty so the editor can
+    // draw, and returns the original stdout for writing a buffer to on exit.
+    pub fn detach_stdout() -> Result<Option<File>, std::io::Error> {
+        if stdout().is_terminal() {
+            return Ok(None);
+        }
+        let tty = termion::get_tty()?;
+        // SAFETY: `dup` hands us a new descriptor that only `original` owns,
+        // and `dup2` only replaces what fd 1 refers to.
+        let original = unsafe {
+            let fd = libc::dup(libc::STDOUT_FILENO);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_fd(fd)
+        };
+        if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Some(original))
+    }
+
     pub fn size(&self) -> &Size {
         &self.size
     }
@@ -46,21 +93,25 @@ impl<'a> Terminal<'a> {
     }
 
     pub fn set_cursor_position(position: &Position) {
-        let Position { mut x, mut y } = &position;
-        x = x.saturating_add(1);
-        y = y.saturating_add(1);
-        print!("{}", termion::cursor::Goto(x as u16, y as u16));
+        let x = u16::try_from(position.x.saturating_add(1)).unwrap_or(u16::MAX);
+        let y = u16::try_from(position.y.saturating_add(1)).unwrap_or(u16::MAX);
+        print!("{}", termion::cursor::Goto(x, y));
     }
 
     pub fn flush() -> Result<(), std::io::Error> {
         io::stdout().flush()
     }
 
-    pub fn read_key() -> Result<Key, std::io::Error> {
-        loop {
-            if let Some(key) = io::stdin().lock().keys().next() {
-                return key;
-            }
+    pub fn read_key(&mut self) -> Result<Key, std::io::Error> {
+        self.keys.recv().map_err(|_| input_closed())?
+    }
+
+    // Waits at most `timeout` for a key.
+    pub fn poll_key(&mut self, timeout: Duration) -> Result<Option<Key>, std::io::Error> {
+        match self.keys.recv_timeout(timeout) {
+            Ok(key) => key.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(input_closed()),
         }
     }
 
@@ -72,19 +123,39 @@ impl<'a> Terminal<'a> {
         print!("{}", termion::cursor::Show);
     }
 
+    pub fn bold() {
+        print!("{}", termion::style::Bold);
+    }
+
+    pub fn underline() {
+        print!("{}", termion::style::Underline);
+    }
+
+    pub fn invert_colors() {
+        print!("{}", termion::style::Invert);
+    }
+
+    pub fn reset_colors() {
+        print!("{}", termion::style::Reset);
+    }
+
     pub fn set_bg_color(color: color::Rgb) {
-        print!("{}", color::Bg(color))
+        print!("{}", color::Bg(color));
     }
 
     pub fn reset_bg_color() {
-        print!("{}", color::Bg(color::Reset))
+        print!("{}", color::Bg(color::Reset));
     }
 
     pub fn set_fg_color(color: color::Rgb) {
-        print!("{}", color::Fg(color))
+        print!("{}", color::Fg(color));
     }
 
     pub fn reset_fg_color() {
-        print!("{}", color::Fg(color::Reset))
+        print!("{}", color::Fg(color::Reset));
     }
 }
+
+fn input_closed() -> std::io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "The terminal input was closed")
+}
@@ -0,0 +1,62 @@
+use crate::Row;
+
+const UNDO_LIMIT: usize = 1000;
+
+// One change to a document: the rows starting at `at` were `before` and
+// became `after`.
+#[derive(Debug)]
+pub struct Edit {
+    pub at: usize,
+    pub before: Vec<Row>,
+    pub after: Vec<Row>,
+}
+
+// The edits made to a document, grouped into the steps undo and redo take.
+// Edits are collected in `pending` until the editor commits them, usually
+// once per key press.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    done: Vec<Vec<Edit>>,
+    undone: Vec<Vec<Edit>>,
+    pending: Vec<Edit>,
+}
+
+impl UndoStack {
+    pub fn record(&mut self, edit: Edit) {
+        self.undone.clear();
+        // Typing into one row only needs that row's first and last states.
+        if let Some(last) = self.pending.last_mut() {
+            if last.at == edit.at && last.after.len() == 1 && edit.before.len() == 1 {
+                last.after = edit.after;
+                return;
+            }
+        }
+        self.pending.push(edit);
+    }
+
+    // Closes the current step, so the next edit starts a new one.
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.done.push(std::mem::take(&mut self.pending));
+        if self.done.len() > UNDO_LIMIT {
+            self.done.remove(0);
+        }
+    }
+
+    // The step to revert, with its edits in the order they were made.
+    pub fn undo(&mut self) -> Option<&[Edit]> {
+        self.commit();
+        let step = self.done.pop()?;
+        self.undone.push(step);
+        self.undone.last().map(Vec::as_slice)
+    }
+
+    pub fn redo(&mut self) -> Option<&[Edit]> {
+        self.commit();
+        let step = self.undone.pop()?;
+        self.done.push(step);
+        self.done.last().map(Vec::as_slice)
+    }
+}
@@ -0,0 +1,618 @@
+use regex::Regex;
+use std::collections::HashMap;
+use termion::event::Key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl Mode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::VisualLine => "V-LINE",
+        }
+    }
+
+    pub fn is_visual(self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    NextLineStart,
+    WordForward {
+        big: bool,
+    },
+    WordBackward {
+        big: bool,
+    },
+    WordEnd {
+        big: bool,
+    },
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FileStart,
+    FileEnd,
+    Find {
+        target: String,
+        forward: bool,
+        till: bool,
+    },
+    RepeatFind {
+        reverse: bool,
+    },
+    ParagraphForward,
+    ParagraphBackward,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    Word { big: bool },
+    Quote(String),
+    Bracket(&'static str, &'static str),
+    Paragraph,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Motion(Motion),
+    Object { object: TextObject, inner: bool },
+    Line,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertPoint {
+    Cursor,
+    After,
+    LineStart,
+    LineEnd,
+    LineBelow,
+    LineAbove,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    Insert(InsertPoint),
+    Put { before: bool },
+    Replace(String),
+    Repeat,
+    Visual { linewise: bool },
+    CommandLine,
+    OperateSelection(Operator),
+    SwapAnchor,
+    ExitVisual,
+    JumpForward,
+    Undo,
+    Hover,
+    GotoDefinition,
+    FindReferences,
+    NextHunk,
+    PreviousHunk,
+    DiffObtain,
+    DiffPut,
+    NextConflict,
+    PreviousConflict,
+    ToggleFold,
+    CloseFold,
+    OpenFold,
+    CloseAllFolds,
+    OpenAllFolds,
+}
+
+pub struct NormalCommand {
+    pub register: Option<char>,
+    pub count: Option<usize>,
+    pub action: Action,
+}
+
+impl NormalCommand {
+    pub fn count(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+
+    // Whether `.` should be able to repeat this command.
+    pub fn is_change(&self) -> bool {
+        match &self.action {
+            Action::Operate(operator, _) => *operator != Operator::Yank,
+            Action::Insert(_) | Action::Put { .. } | Action::Replace(_) => true,
+            _ => false,
+        }
+    }
+}
+
+enum Parse {
+    Incomplete,
+    Invalid,
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Result<char, Parse> {
+        let c = self
+            .chars
+            .get(self.index)
+            .copied()
+            .ok_or(Parse::Incomplete)?;
+        self.index += 1;
+        Ok(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn count(&mut self) -> Option<usize> {
+        if !matches!(self.peek(), Some('1'..='9')) {
+            return None;
+        }
+        let mut count: usize = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            count = count.saturating_mul(10).saturating_add(digit as usize);
+            self.index += 1;
+        }
+
+        Some(count)
+    }
+
+    fn motion(&mut self, c: char) -> Result<Option<Motion>, Parse> {
+        let motion = match c {
+            'h' => Motion::Left,
+            'l' | ' ' => Motion::Right,
+            'k' => Motion::Up,
+            'j' => Motion::Down,
+            '\n' | '+' => Motion::NextLineStart,
+            'w' | 'W' => Motion::WordForward { big: c == 'W' },
+            'b' | 'B' => Motion::WordBackward { big: c == 'B' },
+            'e' | 'E' => Motion::WordEnd { big: c == 'E' },
+            '0' => Motion::LineStart,
+            '^' => Motion::FirstNonBlank,
+            '$' => Motion::LineEnd,
+            'G' => Motion::FileEnd,
+            '}' => Motion::ParagraphForward,
+            '{' => Motion::ParagraphBackward,
+            ';' | ',' => Motion::RepeatFind { reverse: c == ',' },
+            'g' => match self.next()? {
+                'g' => Motion::FileStart,
+                _ => return Err(Parse::Invalid),
+            },
+            'f' | 'F' | 't' | 'T' => Motion::Find {
+                target: self.next()?.to_string(),
+                forward: c.is_lowercase(),
+                till: c.eq_ignore_ascii_case(&'t'),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(motion))
+    }
+
+    fn text_object(&mut self) -> Result<TextObject, Parse> {
+        let object = match self.next()? {
+            'w' => TextObject::Word { big: false },
+            'W' => TextObject::Word { big: true },
+            'p' => TextObject::Paragraph,
+            quote @ ('"' | '\'' | '`') => TextObject::Quote(quote.to_string()),
+            '(' | ')' | 'b' => TextObject::Bracket("(", ")"),
+            '{' | '}' | 'B' => TextObject::Bracket("{", "}"),
+            '[' | ']' => TextObject::Bracket("[", "]"),
+            '<' | '>' => TextObject::Bracket("<", ">"),
+            _ => return Err(Parse::Invalid),
+        };
+
+        Ok(object)
+    }
+
+    fn command(&mut self, visual: bool) -> Result<NormalCommand, Parse> {
+        let mut register = None;
+        if self.peek() == Some('"') {
+            self.index += 1;
+            register = Some(self.next()?);
+        }
+        let mut count = self.count();
+        let c = self.next()?;
+
+        if !visual {
+            let action = match (c, self.peek()) {
+                ('g', Some('d')) => Some(Action::GotoDefinition),
+                ('g', Some('r')) => Some(Action::FindReferences),
+                ('d', Some('o')) => Some(Action::DiffObtain),
+                ('d', Some('p')) => Some(Action::DiffPut),
+                _ => None,
+            };
+            if let Some(action) = action {
+                self.index += 1;
+                return Ok(NormalCommand {
+                    register,
+                    count,
+                    action,
+                });
+            }
+        }
+
+        if matches!(c, ']' | '[' | 'z') && !visual {
+            let action = prefixed_action(c, self.next()?).ok_or(Parse::Invalid)?;
+            return Ok(NormalCommand {
+                register,
+                count,
+                action,
+            });
+        }
+
+        if let Some(motion) = self.motion(c)? {
+            return Ok(NormalCommand {
+                register,
+                count,
+                action: Action::Move(motion),
+            });
+        }
+
+        let action = match (c, visual) {
+            ('d' | 'x', true) => Action::OperateSelection(Operator::Delete),
+            ('c' | 's', true) => Action::OperateSelection(Operator::Change),
+            ('y', true) => Action::OperateSelection(Operator::Yank),
+            ('o', true) => Action::SwapAnchor,
+            ('d' | 'c' | 'y', false) => {
+                let operator = match c {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    _ => Operator::Yank,
+                };
+                if let Some(inner_count) = self.count() {
+                    count = Some(count.unwrap_or(1).saturating_mul(inner_count));
+                }
+                let next = self.next()?;
+                let target = if next == c {
+                    Target::Line
+                } else if next == 'i' || next == 'a' {
+                    Target::Object {
+                        object: self.text_object()?,
+                        inner: next == 'i',
+                    }
+                } else {
+                    Target::Motion(self.motion(next)?.ok_or(Parse::Invalid)?)
+                };
+                Action::Operate(operator, target)
+            }
+            ('x', false) => Action::Operate(Operator::Delete, Target::Motion(Motion::Right)),
+            ('X', false) => Action::Operate(Operator::Delete, Target::Motion(Motion::Left)),
+            ('D', false) => Action::Operate(Operator::Delete, Target::Motion(Motion::LineEnd)),
+            ('C', false) => Action::Operate(Operator::Change, Target::Motion(Motion::LineEnd)),
+            ('s', false) => Action::Operate(Operator::Change, Target::Motion(Motion::Right)),
+            ('S', false) => Action::Operate(Operator::Change, Target::Line),
+            ('Y', false) => Action::Operate(Operator::Yank, Target::Line),
+            ('i', false) => Action::Insert(InsertPoint::Cursor),
+            ('a', false) => Action::Insert(InsertPoint::After),
+            ('I', false) => Action::Insert(InsertPoint::LineStart),
+            ('A', false) => Action::Insert(InsertPoint::LineEnd),
+            ('o', false) => Action::Insert(InsertPoint::LineBelow),
+            ('O', false) => Action::Insert(InsertPoint::LineAbove),
+            ('p' | 'P', false) => Action::Put { before: c == 'P' },
+            ('r', false) => Action::Replace(self.next()?.to_string()),
+            ('.', false) => Action::Repeat,
+            ('\t', false) => Action::JumpForward,
+            ('u', false) => Action::Undo,
+            ('K', false) => Action::Hover,
+            ('v' | 'V', _) => Action::Visual { linewise: c == 'V' },
+            (':', _) => Action::CommandLine,
+            _ => return Err(Parse::Invalid),
+        };
+
+        Ok(NormalCommand {
+            register,
+            count,
+            action,
+        })
+    }
+}
+
+// The actions named by `[`, `]` or `z` and the key after it.
+fn prefixed_action(prefix: char, c: char) -> Option<Action> {
+    Some(match (prefix, c) {
+        (']', 'c') => Action::NextHunk,
+        ('[', 'c') => Action::PreviousHunk,
+        (']', 'x') => Action::NextConflict,
+        ('[', 'x') => Action::PreviousConflict,
+        ('z', 'a') => Action::ToggleFold,
+        ('z', 'c') => Action::CloseFold,
+        ('z', 'o') => Action::OpenFold,
+        ('z', 'M') => Action::CloseAllFolds,
+        ('z', 'R') => Action::OpenAllFolds,
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Default)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+pub struct Vim {
+    pub mode: Mode,
+    pending: Vec<Key>,
+    registers: HashMap<char, Register>,
+    pub last_find: Option<(String, bool, bool)>,
+    recording: Option<Vec<Key>>,
+    last_change: Vec<Key>,
+    pub insert_count: usize,
+    pub insert_point: InsertPoint,
+    pub inserted: Vec<Key>,
+}
+
+impl Vim {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Normal,
+            pending: Vec::new(),
+            registers: HashMap::new(),
+            last_find: None,
+            recording: None,
+            last_change: Vec::new(),
+            insert_count: 1,
+            insert_point: InsertPoint::Cursor,
+            inserted: Vec::new(),
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Adds a key to the command being typed. Returns the command and the keys
+    // that made it up once they form a complete command.
+    pub fn feed(&mut self, key: Key) -> Option<(NormalCommand, Vec<Key>)> {
+        self.pending.push(key);
+        if key == Key::Esc {
+            self.pending.clear();
+            return self.mode.is_visual().then(|| {
+                let command = NormalCommand {
+                    register: None,
+                    count: None,
+                    action: Action::ExitVisual,
+                };
+                (command, vec![key])
+            });
+        }
+
+        let chars: Option<Vec<char>> = self
+            .pending
+            .iter()
+            .map(|key| match key {
+                Key::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+        let Some(chars) = chars else {
+            self.pending.clear();
+            return None;
+        };
+        let mut parser = Parser {
+            chars: &chars,
+            index: 0,
+        };
+        match parser.command(self.mode.is_visual()) {
+            Ok(command) => Some((command, std::mem::take(&mut self.pending))),
+            Err(Parse::Incomplete) => None,
+            Err(Parse::Invalid) => {
+                self.pending.clear();
+                None
+            }
+        }
+    }
+
+    pub fn register(&self, name: Option<char>) -> Option<&Register> {
+        self.registers
+            .get(&name.unwrap_or('"').to_ascii_lowercase())
+    }
+
+    pub fn store(&mut self, name: Option<char>, register: Register, yank: bool) {
+        match name {
+            Some('_') => return,
+            Some(name) if name.is_ascii_uppercase() => {
+                let existing = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                if existing.linewise || register.linewise {
+                    existing.text.push('\n');
+                }
+                existing.text.push_str(&register.text);
+                existing.linewise |= register.linewise;
+            }
+            Some(name) if name != '"' => {
+                self.registers.insert(name, register.clone());
+            }
+            _ => (),
+        }
+        if yank && name.is_none() {
+            self.registers.insert('0', register.clone());
+        }
+        self.registers.insert('"', register);
+    }
+
+    pub fn start_change(&mut self, keys: Vec<Key>) {
+        self.recording = Some(keys);
+    }
+
+    pub fn record(&mut self, key: Key) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(key);
+        }
+    }
+
+    pub fn finish_change(&mut self) {
+        if let Some(keys) = self.recording.take() {
+            self.last_change = keys;
+        }
+    }
+
+    // The keys to replay for `.`, with `count` replacing the original count.
+    pub fn repeat_keys(&self, count: Option<usize>) -> Vec<Key> {
+        let Some(count) = count else {
+            return self.last_change.clone();
+        };
+        let mut keys = self.last_change.as_slice();
+        let mut register = Vec::new();
+        if let [Key::Char('"'), name, rest @ ..] = keys {
+            register = vec![Key::Char('"'), *name];
+            keys = rest;
+        }
+        while let [Key::Char('0'..='9'), rest @ ..] = keys {
+            keys = rest;
+        }
+
+        register
+            .into_iter()
+            .chain(count.to_string().chars().map(Key::Char))
+            .chain(keys.iter().copied())
+            .collect()
+    }
+}
+
+// Splits the line range off the front of an ex command. `current` and `last`
+// are the cursor row and the last row, and `selection` is the visual
+// selection the command line was opened from, if any.
+pub fn parse_ex_range(
+    input: &str,
+    current: usize,
+    last: usize,
+    selection: Option<(usize, usize)>,
+) -> (Option<(usize, usize)>, &str) {
+    if let Some(rest) = input.strip_prefix('%') {
+        return (Some((0, last)), rest);
+    }
+    if let Some(rest) = input.strip_prefix("'<,'>") {
+        return (selection.or(Some((current, current))), rest);
+    }
+
+    let address = |input: &str| -> Option<(usize, usize)> {
+        match input.chars().next()? {
+            '.' => Some((current, 1)),
+            '$' => Some((last, 1)),
+            c if c.is_ascii_digit() => {
+                let digits = input.chars().take_while(char::is_ascii_digit).count();
+                let line: usize = input[..digits].parse().ok()?;
+                Some((line.saturating_sub(1).min(last), digits))
+            }
+            _ => None,
+        }
+    };
+    let Some((first, used)) = address(input) else {
+        return (selection, input);
+    };
+    let rest = &input[used..];
+    if let Some(after_comma) = rest.strip_prefix(',') {
+        if let Some((second, used)) = address(after_comma) {
+            let (first, second) = (first.min(second), first.max(second));
+            return (Some((first, second)), &after_comma[used..]);
+        }
+    }
+
+    (Some((first, first)), rest)
+}
+
+pub struct Substitution {
+    pub pattern: Regex,
+    pub replacement: String,
+    pub global: bool,
+}
+
+// Parses the `/pattern/replacement/flags` part of `:s`. The pattern uses
+// regex crate syntax; the replacement understands vim's `&` and `\1`.
+pub fn parse_substitute(args: &str) -> Result<Substitution, String> {
+    let mut chars = args.chars();
+    let delimiter = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+        .ok_or_else(|| String::from("Usage: s/pattern/replacement/[gi]"))?;
+
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in chars {
+        if c == delimiter && !escaped && parts.len() < 3 {
+            parts.push(String::new());
+            continue;
+        }
+        let part = parts.last_mut().expect("parts is never empty");
+        if escaped {
+            if c != delimiter {
+                part.push('\\');
+            }
+            part.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else {
+            part.push(c);
+        }
+    }
+    let pattern = parts[0].replace("\\<", "\\b").replace("\\>", "\\b");
+    let replacement = parts.get(1).map_or("", String::as_str);
+    let flags = parts.get(2).map_or("", String::as_str);
+    if pattern.is_empty() {
+        return Err(String::from("Empty search pattern"));
+    }
+    if let Some(flag) = flags.chars().find(|c| !matches!(c, 'g' | 'i')) {
+        return Err(format!("Unknown substitute flag '{flag}'"));
+    }
+
+    let pattern = if flags.contains('i') {
+        format!("(?i){pattern}")
+    } else {
+        pattern
+    };
+    let pattern = Regex::new(&pattern).map_err(|err| format!("Invalid pattern: {err}"))?;
+
+    Ok(Substitution {
+        pattern,
+        replacement: convert_replacement(replacement),
+        global: flags.contains('g'),
+    })
+}
+
+fn convert_replacement(replacement: &str) -> String {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => result.push_str("${0}"),
+            '$' => result.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    result.push_str("${");
+                    result.push(digit);
+                    result.push('}');
+                }
+                Some('$') => result.push_str("$$"),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            },
+            _ => result.push(c),
+        }
+    }
+
+    result
+}